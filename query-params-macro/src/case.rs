@@ -0,0 +1,113 @@
+//! Renaming of snake_case field names, shared by `#[query(rename_all = "...")]` and, with the
+//! `serde-compat` feature, `#[serde(rename_all = "...")]`.
+use crate::{FieldAttributes, FieldDescription};
+use syn::ext::IdentExt;
+
+/// The casing rules accepted by `rename_all`, matching serde's, applied to snake_case field names.
+///
+/// Field names are split into words on `_`. Words are never split any further, so acronyms are
+/// treated like any other word, e.g. `http_url` becomes `httpUrl` in camelCase rather than
+/// `HTTPUrl`.
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub(crate) enum RenameRule {
+    /// Leaves the name as-is, e.g. `user_id`.
+    LowerCase,
+    /// Uppercases the whole name, keeping underscores, e.g. `USER_ID`.
+    UpperCase,
+    /// Capitalizes the first character of every word and removes the underscores, e.g. `UserId`.
+    PascalCase,
+    /// As `PascalCase`, but with the first character lowercased, e.g. `userId`.
+    CamelCase,
+    /// Leaves the name as-is, e.g. `user_id`.
+    SnakeCase,
+    /// Uppercases the whole name, keeping underscores, e.g. `USER_ID`.
+    ScreamingSnakeCase,
+    /// Replaces underscores with hyphens, e.g. `user-id`.
+    KebabCase,
+    /// Uppercases the whole name and replaces underscores with hyphens, e.g. `USER-ID`.
+    ScreamingKebabCase,
+}
+
+impl RenameRule {
+    /// The names accepted by `rename_all`, in the order they're listed in error messages.
+    pub(crate) const NAMES: &'static [&'static str] = &[
+        "lowercase",
+        "UPPERCASE",
+        "PascalCase",
+        "camelCase",
+        "snake_case",
+        "SCREAMING_SNAKE_CASE",
+        "kebab-case",
+        "SCREAMING-KEBAB-CASE",
+    ];
+
+    pub(crate) fn from_name(name: &str) -> Option<RenameRule> {
+        match name {
+            "lowercase" => Some(RenameRule::LowerCase),
+            "UPPERCASE" => Some(RenameRule::UpperCase),
+            "PascalCase" => Some(RenameRule::PascalCase),
+            "camelCase" => Some(RenameRule::CamelCase),
+            "snake_case" => Some(RenameRule::SnakeCase),
+            "SCREAMING_SNAKE_CASE" => Some(RenameRule::ScreamingSnakeCase),
+            "kebab-case" => Some(RenameRule::KebabCase),
+            "SCREAMING-KEBAB-CASE" => Some(RenameRule::ScreamingKebabCase),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn apply_to_field(&self, field: &str) -> String {
+        match self {
+            RenameRule::LowerCase | RenameRule::SnakeCase => field.to_string(),
+            RenameRule::UpperCase | RenameRule::ScreamingSnakeCase => field.to_ascii_uppercase(),
+            RenameRule::PascalCase => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for ch in field.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(ch);
+                    }
+                }
+                pascal
+            }
+            RenameRule::CamelCase => {
+                let pascal = RenameRule::PascalCase.apply_to_field(field);
+                let mut chars = pascal.chars();
+                match chars.next() {
+                    Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+                    None => pascal,
+                }
+            }
+            RenameRule::KebabCase => field.replace('_', "-"),
+            RenameRule::ScreamingKebabCase => field.to_ascii_uppercase().replace('_', "-"),
+        }
+    }
+}
+
+/// Renames every field that doesn't carry an explicit `#[query(rename = "...")]` using `rule`.
+pub(crate) fn apply_rename_all<'f>(
+    rule: Option<RenameRule>,
+    mut field_descriptions: Vec<FieldDescription<'f>>,
+) -> Vec<FieldDescription<'f>> {
+    let Some(rule) = rule else {
+        return field_descriptions;
+    };
+
+    for desc in field_descriptions.iter_mut() {
+        let explicit_rename = desc
+            .attributes
+            .iter()
+            .any(|attr| matches!(attr, FieldAttributes::Rename(_)));
+
+        if !explicit_rename {
+            desc.field_name = rule.apply_to_field(&desc.ident.unraw().to_string());
+        }
+    }
+
+    field_descriptions
+}
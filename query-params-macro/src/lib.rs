@@ -4,25 +4,118 @@
 //! and is not meant for direct consumption.*
 //!
 //! [`Hyper`]: https://crates.io/crates/hyper
+use case::RenameRule;
 use proc_macro::{self, TokenStream};
-use quote::quote;
+use quote::{format_ident, quote};
 use std::collections::HashSet;
 use std::vec::Vec;
 use syn::__private::TokenStream2;
-use syn::{parse_macro_input, Attribute, DeriveInput, Field, Fields, Ident, LitStr, Path, Type};
+use syn::ext::IdentExt;
+use syn::meta::ParseNestedMeta;
+use syn::{
+    parse_macro_input, Attribute, DataEnum, DeriveInput, ExprPath, Field, Fields, GenericArgument,
+    Ident, Lit, LitBool, LitInt, LitStr, Path, PathArguments, Token, Type,
+};
 
-#[derive(Debug, Eq, PartialEq, Hash)]
+mod case;
+#[cfg(feature = "serde-compat")]
+mod serde_compat;
+#[cfg(feature = "utoipa")]
+mod utoipa_support;
+
+#[derive(Debug, Eq, PartialEq)]
 enum FieldAttributes {
     Required,
     Excluded,
     Rename(String),
+    Alias(String),
+    Raw,
+    Encode(String),
+    Format(String),
+    Epoch(String),
+    Duration(String),
+    Default,
+    Unwrap,
+    SkipIf(String),
+    SkipFieldIf(String),
+    ExcludeIf(String),
+    When(String),
+    Sensitive,
+    NonePair(String, String),
+    Transform(String),
+    WithTry(String),
+    Min(String),
+    Max(String),
+    NonEmpty,
+    Nested,
+    Indexed,
+    Flatten,
+    BracketIpv6,
+    BoolFormat(String, String),
+    Order(u32),
+    #[cfg(feature = "uuid")]
+    Uuid(String),
+    #[cfg(feature = "base64")]
+    Base64,
+    #[cfg(feature = "serde_json")]
+    Json,
+    #[cfg(feature = "serde_json")]
+    ValueObject,
+    #[cfg(feature = "chrono")]
+    DatetimeFormat(String),
+    #[cfg(feature = "time")]
+    TimeFormat(String),
+    #[cfg(feature = "regex")]
+    Pattern(String),
+}
+
+#[derive(Debug, Eq, PartialEq, Hash)]
+enum ContainerAttributes {
+    Sort,
+    AllowDuplicateKeys,
+    Unencoded,
+    Crate(String),
+    RenameAll(RenameRule),
+    KeepNone,
+    Display,
+    IntoIterator,
+    KeyConsts,
+    Exclusive(Vec<SpannedName>),
+    MaxLength(usize),
+    RedactWith(String),
+    Method(Ident),
+    #[cfg(feature = "utoipa")]
+    Utoipa,
+}
+
+/// A field name given in a container attribute, keeping its span for errors. Only the name is
+/// compared and hashed.
+#[derive(Debug, Clone)]
+struct SpannedName {
+    name: String,
+    span: proc_macro2::Span,
+}
+
+impl PartialEq for SpannedName {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for SpannedName {}
+
+impl std::hash::Hash for SpannedName {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+    }
 }
 
 struct FieldDescription<'f> {
     pub field: &'f Field,
     pub field_name: String,
     pub ident: Ident,
-    pub attributes: HashSet<FieldAttributes>,
+    pub attributes: Vec<FieldAttributes>,
+    pub transforms: Vec<ExprPath>,
 }
 
 /// [`QueryParams`] derives `fn to_query_params(&self) -> Vec<(String, String)>` for
@@ -31,17 +124,15 @@ struct FieldDescription<'f> {
 /// Optional values are only included if present,
 /// and fields marked `#[query(required)]` must be non-optional. Renaming and excluding of fields is
 /// also available, using `#[query(rename = "new_name")]` or `#[query(exclude)]` on the field.
+/// Pairs are produced in the order the fields are declared, unless reordered with
+/// `#[query(order = N)]`.
 ///
 /// # Example: Query Params
 /// QueryParams supports both required and optional fields, which won't be included in the output
 /// if their value is None.
 ///
 /// ```
-/// # use query_params_macro::QueryParams;
-/// # // trait defined here again since it can't be provided by macro crate
-/// # pub trait ToQueryParams {
-/// #    fn to_query_params(&self) -> Vec<(String, String)>;
-/// # }
+/// # use to_query_params::{QueryParams, ToQueryParams};
 /// // Eq and PartialEq are just for assertions
 /// #[derive(QueryParams, Debug, PartialEq, Eq)]
 /// struct ProductRequest {
@@ -73,10 +164,217 @@ struct FieldDescription<'f> {
 /// QueryParams supports attributes under `#[query(...)]` on individual fields to carry metadata.
 /// At this time, the available attributes are:
 /// - required -- marks a field as required, meaning it can be `T` instead of `Option<T>` on the struct
-/// and will always appear in the resulting `Vec`
+///   and will always appear in the resulting `Vec`
 /// - rename -- marks a field to be renamed when it is output in the resulting Vec.
-/// E.g. `#[query(rename = "newName")]`
+///   E.g. `#[query(rename = "newName")]`
+/// - alias -- also emits the field's value under an additional key, right after the pair with the
+///   field's own key, e.g. to send both the old and new name during an API migration. Can be
+///   repeated, and aliases are emitted in the order they're declared.
+///   E.g. `#[query(rename = "pageSize", alias = "page_size", alias = "limit")]`
 /// - exclude -- marks a field to never be included in the output query params
+/// - raw -- marks a field whose key and value are already percent-encoded, so `to_encoded_params`
+///   emits them as-is instead of encoding them again
+/// - encode -- controls which parts of the pair `to_encoded_params` encodes, one of `"both"` (the
+///   default), `"value_only"`, `"key_only"` or `"none"`, e.g. `#[query(encode = "value_only")]`
+///   to keep the brackets in a key like `items[]` literal. `#[query(raw)]` is equivalent to
+///   `#[query(encode = "none")]`. It can also choose how both parts are encoded instead:
+///   `"component"` leaves every character allowed in a query component as-is, like the `/` and `?`
+///   of a URL passed as a value, as `EncodeSet::QueryLenient` does, and `"form"` follows the
+///   `application/x-www-form-urlencoded` rules of `EncodeSet::FormUrlEncoded`, encoding spaces as
+///   `+`. Other fields keep the standard encoding, where `/` becomes `%2F` and spaces `%20`
+/// - format -- formats the field's value with a format string containing exactly one positional
+///   placeholder, instead of `to_string()`. E.g. `#[query(format = "{:.2}")]` or
+///   `#[query(format = "{:08x}")]`
+/// - skip_if -- skips the field when a predicate returns true. The predicate receives a reference
+///   to the field's value, so `fn(&T) -> bool` for both `T` and `Option<T>` fields, and is only
+///   called for `Some` values. E.g. `#[query(skip_if = "String::is_empty")]`
+/// - skip_field_if -- skips the field when a predicate returns true, mirroring serde's
+///   `skip_serializing_if`. The predicate receives a reference to the whole field, so
+///   `fn(&Option<T>) -> bool` for optional fields. `None` values are still never included.
+///   E.g. `#[query(skip_field_if = "Option::is_none")]`
+/// - exclude_if -- excludes the field when a predicate returns true. Unlike `skip_if` and
+///   `skip_field_if`, the predicate receives a reference to the whole struct, so
+///   `fn(&Self) -> bool`, allowing the decision to depend on other fields.
+///   E.g. `#[query(exclude_if = "Self::is_anonymous")]`
+/// - sensitive -- replaces the field's value with `***` in `to_redacted_params`, e.g. for API keys
+///   that shouldn't end up in logs. The other methods emit the real value. On a `nested` field,
+///   every pair of the child is redacted
+/// - when -- the opposite of `exclude_if`, only includes the field when a `fn(&Self) -> bool`
+///   predicate returns true, e.g. to send a signature only for signed requests.
+///   E.g. `#[query(when = "Self::is_signed")]`
+/// - none_pair -- emits a different (key, value) pair when an optional field is `None`, instead
+///   of omitting it. E.g. `#[query(none_pair("mode" = "default"))]`
+/// - default -- emits the type's default value when an optional field is `None`, instead of
+///   omitting it, so `Option<T>` fields require `T: Default`. E.g. `#[query(default)]` on an
+///   `Option<u32>` emits `0`
+/// - unwrap -- always emits an `Option<T>` field as if it were required, for values that are known
+///   to be present, panicking with "query field `name` was None, but is marked #[query(unwrap)]"
+///   if it's `None`. Can't be combined with `required`, `none_pair` or `default`
+/// - transform -- applies a `fn(String) -> String` to the field's value after it's been converted
+///   to a `String`. Repeated transforms are applied in the order they're declared.
+///   E.g. `#[query(transform = "str_utils::trim", transform = "str_utils::lowercase")]`
+/// - with_try -- formats the field's value with a fallible `fn(&T) -> Result<String, E>` instead of
+///   `to_string`, where `E` converts into `Box<dyn Error + Send + Sync>`. Errors are returned from
+///   `try_to_query_params`, naming the field, and the other methods panic with them.
+///   E.g. `#[query(with_try = "limits::check_page_size")]`
+/// - min, max -- checks that the field's value is at least `min` or at most `max`, given as a
+///   literal of the field's type, e.g. `#[query(min = 1, max = 100)]` on a `u32` or
+///   `#[query(min = 0.5)]` on an `f64`
+/// - non_empty -- checks that the field's value isn't empty, for types with an `is_empty` method
+///   like `String` and `Vec<T>`
+/// - pattern -- with the `regex` feature, checks that the field's `AsRef<str>` value matches a
+///   regex. E.g. `#[query(pattern = "^[a-z-]+$")]`. Invalid patterns are a compile error
+/// - epoch -- for `SystemTime` fields, chooses the unit of the emitted time since the Unix epoch,
+///   either `#[query(epoch = "secs")]` (the default) or `#[query(epoch = "millis")]`
+/// - duration -- for `Duration` fields, chooses the unit the duration is emitted in, either
+///   `#[query(duration = "secs")]` (the default) or `#[query(duration = "millis")]`
+/// - nested -- for fields whose type implements `ToQueryParams`, emits the child's pairs with
+///   each key wrapped in brackets under the field's name, e.g. `address[city]`. The child's pairs
+///   are url-encoded by the child, keeping its own encoding attributes. An `Option` of a nested
+///   struct emits nothing when `None`
+/// - indexed -- for `Vec<T>` fields, or other collections iterating over `&T`, where `T` implements
+///   `ToQueryParams`, emits each item's pairs with its index and key in brackets under the field's
+///   name, counting from 0, e.g. `items[0][name]`. The items' keys are their own, after any
+///   renaming on `T`, and items with `nested` fields add their own brackets, e.g.
+///   `items[0][address][city]`. An empty collection emits nothing
+/// - flatten -- for map fields like `HashMap<String, T>`, or other collections iterating over
+///   `(&K, &V)`, emits each entry directly as a pair, using the entry's key, e.g. to pass through
+///   arbitrary extra parameters. Values are converted with `to_string`. Entries are emitted in the
+///   map's iteration order, so use a `BTreeMap` or `IndexMap` for a stable order. Since the keys
+///   are only known at runtime, they aren't checked against the other fields' keys. Doesn't need
+///   `required`, and an `Option` of a map emits nothing when `None`
+/// - order -- emits the field at a fixed position, for APIs that expect parameters in a specific
+///   order. Fields with an `order` come first, in ascending order, followed by the remaining fields
+///   in declaration order. Each value can only be used once. E.g. `#[query(order = 1)]`
+/// - bool_format -- for `bool` fields, chooses the values emitted for `true` and `false`, separated
+///   by a `/`, for APIs that don't accept `true` and `false`. E.g. `#[query(bool_format = "1/0")]`,
+///   `"yes/no"` or `"on/off"`
+/// - bracket_ipv6 -- for `Ipv6Addr` fields, wraps the address in brackets, e.g. `[::1]`, for APIs
+///   that expect the URL host form. On `IpAddr` fields only IPv6 addresses are wrapped. Without it,
+///   IP addresses use `Display`, so the colons of an IPv6 address are encoded as `%3A`
+///
+/// Any other key inside `#[query(...)]` is a compile error, while attributes other than `query`,
+/// like doc comments or `#[serde(...)]`, are ignored.
+///
+/// With the `uuid` feature enabled, `uuid::Uuid` fields can also be formatted with
+/// `#[query(uuid = "hyphenated")]`, `#[query(uuid = "simple")]` or `#[query(uuid = "urn")]`. Without
+/// the attribute they use `Display`, which is hyphenated.
+///
+/// With the `base64` feature enabled, byte fields (`Vec<u8>`, `&[u8]` or `[u8; N]`) marked with
+/// `#[query(base64)]` are emitted as URL-safe base64 without padding.
+///
+/// With the `serde_json` feature enabled, fields implementing `serde::Serialize` that are marked
+/// with `#[query(json)]` are emitted as a JSON string. Serialization errors, such as maps with
+/// non-string keys, are returned by `try_to_query_params` and cause a panic elsewhere.
+/// `serde_json::Value` fields are emitted as the same compact JSON with or without `json`, since
+/// that's also what their `Display` implementation writes.
+///
+/// Also with the `serde_json` feature, a `serde_json::Value` field marked with
+/// `#[query(value_object)]` must hold a JSON object, and is expanded into one pair per entry in the
+/// object, using the entries' keys. Strings are emitted without quotes, nested arrays and objects
+/// are emitted as JSON strings, and `null` entries are skipped. A non-object value causes a panic.
+///
+/// With the `chrono` feature enabled, `chrono::DateTime` fields are emitted in RFC 3339 format,
+/// and `chrono::NaiveDate` fields as `%Y-%m-%d`. Either can be formatted differently with a
+/// `chrono` format string, e.g. `#[query(datetime_format = "%Y-%m-%d")]`.
+///
+/// With the `time` feature enabled, `time::OffsetDateTime` fields are emitted in RFC 3339 format,
+/// and `time::Date` fields as `YYYY-MM-DD`, as their `Display` does. Either can be formatted
+/// differently with a `time` format description, e.g.
+/// `#[query(time_format = "[year]-[month]-[day]")]`. An invalid format description panics the first
+/// time it's used, and a value the format can't represent is a serialization error, returned by
+/// `try_to_query_params` and panicking elsewhere.
+///
+/// `PathBuf` and `&Path` fields are emitted using a lossy UTF-8 conversion, since they don't
+/// implement `Display`.
+///
+/// `Box<T>`, `Rc<T>` and `Arc<T>` fields, optional or not, are emitted as their `T` would be.
+///
+/// `Result<T, E>` fields are handled like `Option<T>` fields, emitting `T` only for `Ok` values.
+/// `Err` values are silently dropped, as `None` would be.
+///
+/// `SystemTime` fields, which don't implement `Display`, are emitted as the time since the Unix
+/// epoch. Times before the epoch are emitted as `0`.
+///
+/// `std::time::Duration` fields, which also don't implement `Display`, are emitted as a whole
+/// number of seconds, truncating any remainder.
+///
+/// QueryParams also supports attributes under `#[query(...)]` on the struct itself:
+/// - sort -- sorts the output by key, and then by value, making the output canonical regardless
+///   of field order. Sorting happens after renames and covers every pair, including those of
+///   `nested` and `value_object` fields. `#[query(sorted)]` is accepted as an alias, and neither
+///   can be combined with `#[query(order = N)]` on a field
+/// - allow_duplicate_keys -- allows multiple fields to emit the same key, which is otherwise a
+///   compile error since it's usually a mistake in a rename
+/// - display -- also implements `Display` for the struct, writing the url-encoded query string
+///   from `to_query_string`, e.g. for logging
+/// - into_iterator -- also implements `IntoIterator` for references to the struct, yielding the
+///   un-encoded pairs from `to_query_params`, so `&params` can be passed straight to builders
+///   accepting an iterator of pairs
+/// - exclusive -- makes the named fields mutually exclusive, so at most one of them may be set. A
+///   required field is always set, and an optional one when it's `Some`. Only checked by
+///   `try_to_query_params`, which returns a `QueryParamsError::Exclusive` listing the fields that
+///   were set; the other methods emit the pairs as they are. Can be repeated for several groups.
+///   E.g. `#[query(exclusive("cursor", "page"))]`
+/// - redact_with -- the placeholder `to_redacted_params` emits for `sensitive` fields, instead of
+///   `***`. E.g. `#[query(redact_with = "[REDACTED]")]`
+/// - max_length -- the maximum length of the url-encoded query string in bytes, returned by
+///   `max_query_length`. `try_to_query_params` and `try_to_query_string_default` return a
+///   `QueryParamsError::TooLong` with the actual length when it's exceeded, while the other
+///   methods don't check it.
+///   E.g. `#[query(max_length = 8192)]`
+/// - key_consts -- also declares a `KEY_<FIELD>` constant on the struct for each field, holding
+///   its key after renaming, e.g. `ProductRequest::KEY_MAX_PRICE`. Excluded, `nested` and
+///   `value_object` fields don't get one
+/// - skip_none -- `#[query(skip_none = false)]` makes optional fields emit their key with an empty
+///   value when `None`, e.g. `limit=`, instead of omitting them. Fields with their own `none_pair`
+///   or `default` keep it, and `nested` and `value_object` fields are still omitted
+/// - rename_all -- renames every field without a `#[query(rename = "...")]` using one of the
+///   casing rules `"lowercase"`, `"UPPERCASE"`, `"PascalCase"`, `"camelCase"`, `"snake_case"`,
+///   `"SCREAMING_SNAKE_CASE"`, `"kebab-case"` or `"SCREAMING-KEBAB-CASE"`. Field names are split
+///   into words on `_` only, so acronyms are cased like any other word, e.g. `http_url` becomes
+///   `httpUrl` with `#[query(rename_all = "camelCase")]`
+/// - crate -- the path used to refer to this crate in the generated code, for crates that
+///   re-export it, e.g. `#[query(crate = "my_facade::query_params")]`. Defaults to
+///   `::to_query_params`
+/// - method -- also declares an inherent method under the given name, returning the pairs from
+///   `to_query_params`, for structs where that name collides with another trait or inherent
+///   method. The `ToQueryParams` impl is still generated. E.g. `#[query(method = "as_query")]`
+/// - utoipa -- with the `utoipa` feature, also implements `utoipa::IntoParams`, describing each key
+///   from `QUERY_KEYS` as a string query parameter. Keys emitted whether or not the field is set,
+///   like those of `required`, `default` and `unwrap` fields, are marked as required
+/// - encode -- `#[query(encode = false)]` makes `to_encoded_params` emit the same un-encoded pairs
+///   as `to_query_params`, ignoring any field-level encoding attributes, for callers that encode
+///   the pairs themselves. Encoding stays on by default
+///
+/// ## Consuming
+/// The derive also overrides `into_query_params(self)` for structs with `String` fields that are
+/// emitted as-is, moving those values into the output rather than cloning them. It produces the
+/// same pairs as `to_query_params`.
+///
+/// ## Fallible Serialization
+/// The derive also implements `TryToQueryParams`, whose `try_to_query_params` produces the same
+/// pairs as `to_query_params`, but returns a `QueryParamsError` naming the field instead of
+/// panicking when a `json` or `with_try` field fails to serialize. `nested` fields are serialized
+/// with their own `try_to_query_params`, so their types must implement `TryToQueryParams` too.
+///
+/// ## Validation
+/// Fields marked with `min`, `max`, `non_empty` or `pattern` are checked before their pair is
+/// emitted. `try_to_query_params` returns a `QueryParamsError::Invalid` naming the field and the
+/// failed check, while `to_query_params` and the other methods panic with it, so invalid values are
+/// never silently sent. Optional fields are only checked when present, and fields skipped by
+/// `skip_if` aren't checked at all.
+///
+/// ## Query Keys
+/// The derive also generates a `QUERY_KEYS` constant listing the keys the struct can emit, in
+/// declaration order. Keys only known at runtime, like those of `flatten` fields, are left out.
+///
+/// ## Serde Compatibility
+/// With the `serde-compat` feature enabled, fields without a `#[query(rename = "...")]` attribute
+/// fall back to their `#[serde(rename = "...")]` name, or to the struct's
+/// `#[serde(rename_all = "...")]` rule if one is present. A field's `#[query(rename = "...")]`
+/// always takes precedence, and the struct's `#[query(rename_all = "...")]` takes precedence over
+/// `#[serde(rename_all = "...")]`.
 ///
 /// # Example: Renaming and Excluding
 /// In some cases, names of query parameters are not valid identifiers, or don't adhere to Rust's
@@ -89,11 +387,7 @@ struct FieldDescription<'f> {
 /// is also tagged as `#[query(exclude)]` to exclude it.
 ///
 /// ```
-/// # use query_params_macro::QueryParams;
-/// # // trait defined here again since it can't be provided by macro crate
-/// # pub trait ToQueryParams {
-/// #    fn to_query_params(&self) -> Vec<(String, String)>;
-/// # }
+/// # use to_query_params::{QueryParams, ToQueryParams};
 /// // Eq and PartialEq are just for assertions
 /// #[derive(QueryParams, Debug, PartialEq, Eq)]
 /// struct ProductRequest {
@@ -126,14 +420,101 @@ struct FieldDescription<'f> {
 ///     assert_eq!(expected, query_params);
 /// }
 /// ```
+///
+/// # Example: Enum Values
+/// Deriving QueryParams on an enum of unit variants instead emits each variant by name, or by its
+/// `#[query(rename = "...")]`, through a generated `as_query_value` method and `Display` impl, so
+/// struct fields of the enum's type, optional or not, use those names.
+///
+/// ```
+/// # use to_query_params::{QueryParams, ToQueryParams};
+/// #[derive(QueryParams)]
+/// enum Sort {
+///     #[query(rename = "asc")]
+///     Asc,
+///     #[query(rename = "desc")]
+///     Desc,
+/// }
+///
+/// #[derive(QueryParams)]
+/// struct ProductRequest {
+///     sort: Option<Sort>,
+/// }
+///
+/// pub fn main() {
+///     let request = ProductRequest {
+///         sort: Some(Sort::Desc),
+///     };
+///
+///     let expected: Vec<(String, String)> = vec![("sort".into(), "desc".into())];
+///
+///     assert_eq!(expected, request.to_query_params());
+///     assert_eq!("asc", Sort::Asc.as_query_value());
+/// }
+/// ```
+///
+/// # Example: Encoding
+/// `to_encoded_params` url-encodes keys and values once, using the `urlencoding` crate through
+/// `to-query-params`, so it doesn't need to be a direct dependency.
+///
+/// ```
+/// # use to_query_params::{QueryParams, ToQueryParams};
+/// #[derive(QueryParams)]
+/// struct SearchRequest {
+///     #[query(required, rename = "search term")]
+///     term: String,
+/// }
+///
+/// pub fn main() {
+///     let request = SearchRequest {
+///         term: "a&b c".into(),
+///     };
+///
+///     let expected: Vec<(String, String)> = vec![("search%20term".into(), "a%26b%20c".into())];
+///
+///     assert_eq!(expected, request.to_encoded_params());
+/// }
+/// ```
 #[proc_macro_derive(QueryParams, attributes(query))]
 pub fn derive(input: TokenStream) -> TokenStream {
     let ast: DeriveInput = parse_macro_input!(input);
-    let ident = ast.ident;
+
+    derive_query_params(ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn derive_query_params(ast: DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &ast.ident;
+    let mut container_attributes = HashSet::new();
+    for attr in ast.attrs.iter() {
+        container_attributes.extend(parse_container_attributes(attr)?);
+    }
+
+    let sort = container_attributes
+        .contains(&ContainerAttributes::Sort)
+        .then(|| quote!(query_params.sort();));
+
+    let krate = container_attributes
+        .iter()
+        .find_map(|attr| match attr {
+            ContainerAttributes::Crate(path) => Some(syn::parse_str::<Path>(path)),
+            _ => None,
+        })
+        .transpose()?
+        .unwrap_or_else(|| syn::parse_quote!(::to_query_params));
+
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
 
     let fields: &Fields = match ast.data {
         syn::Data::Struct(ref s) => &s.fields,
-        _ => panic!("Can only derive QueryParams for structs."),
+        syn::Data::Enum(ref e) => return derive_enum_value(&ast, e),
+        _ => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "Can only derive QueryParams for structs and enums.",
+            ))
+        }
     };
 
     let named_fields: Vec<&Field> = fields
@@ -144,168 +525,2575 @@ pub fn derive(input: TokenStream) -> TokenStream {
     let field_descriptions = named_fields
         .into_iter()
         .map(map_field_to_description)
+        .collect::<syn::Result<Vec<FieldDescription>>>()?
+        .into_iter()
         .filter(|field| !field.attributes.contains(&FieldAttributes::Excluded))
         .collect::<Vec<FieldDescription>>();
 
-    let required_fields: Vec<&FieldDescription> = field_descriptions
+    let rename_all = container_attributes.iter().find_map(|attr| match attr {
+        ContainerAttributes::RenameAll(rule) => Some(*rule),
+        _ => None,
+    });
+    let field_descriptions = case::apply_rename_all(rename_all, field_descriptions);
+
+    #[cfg(feature = "serde-compat")]
+    let field_descriptions =
+        serde_compat::apply_serde_names(&ast.attrs, rename_all.is_some(), field_descriptions);
+
+    let field_descriptions = if container_attributes.contains(&ContainerAttributes::KeepNone) {
+        keep_none_fields(field_descriptions)
+    } else {
+        field_descriptions
+    };
+
+    if !container_attributes.contains(&ContainerAttributes::AllowDuplicateKeys) {
+        validate_unique_keys(&field_descriptions)?;
+    }
+
+    let mut exclusive_groups: Vec<&Vec<SpannedName>> = container_attributes
         .iter()
-        .filter(|desc| desc.attributes.contains(&FieldAttributes::Required))
+        .filter_map(|attr| match attr {
+            ContainerAttributes::Exclusive(names) => Some(names),
+            _ => None,
+        })
         .collect();
+    exclusive_groups.sort_by_key(|names| names.first().map(|name| name.name.clone()));
+
+    let exclusive_groups = exclusive_groups
+        .into_iter()
+        .map(|names| exclusive_group(names, &field_descriptions))
+        .collect::<syn::Result<Vec<Vec<&FieldDescription>>>>()?;
 
-    let req_names: Vec<String> = required_fields
+    let required_fields: Vec<&FieldDescription> = field_descriptions
         .iter()
-        .map(|field| field.field_name.clone())
+        .filter(|desc| desc.attributes.contains(&FieldAttributes::Required))
         .collect();
 
-    let req_idents: Vec<&Ident> = required_fields.iter().map(|field| &field.ident).collect();
+    for field in required_fields.iter() {
+        let has_none_pair = field
+            .attributes
+            .iter()
+            .any(|attr| matches!(attr, FieldAttributes::NonePair(..)));
 
-    let vec_definition = quote! {
-        let mut query_params: ::std::vec::Vec<(String, String)> =
-        vec![#((
-            #req_names.to_string(),
-            self.#req_idents.to_string()
-        )),*];
-    };
+        if has_none_pair {
+            return Err(syn::Error::new_spanned(
+                &field.ident,
+                "#[query(none_pair(...))] can only be used on optional fields",
+            ));
+        }
 
-    let vec_encoded_definition = quote! {
-        let mut query_params: ::std::vec::Vec<(String, String)> =
-        vec![#(
-            (
-                ::to_query_params::urlencoding::encode(#req_names).into_owned(),
-                ::to_query_params::urlencoding::encode(&self.#req_idents.to_string()).into_owned()
-            )
-        ),*];
-    };
+        if field.attributes.contains(&FieldAttributes::Default) {
+            return Err(syn::Error::new_spanned(
+                &field.ident,
+                "#[query(default)] can only be used on optional fields",
+            ));
+        }
+
+        if field.attributes.contains(&FieldAttributes::Unwrap) {
+            return Err(syn::Error::new_spanned(
+                &field.ident,
+                "#[query(unwrap)] can't be combined with #[query(required)]",
+            ));
+        }
+
+        validate_required_field(field)?;
+    }
 
     let optional_fields: Vec<&FieldDescription> = field_descriptions
         .iter()
         .filter(|desc| !desc.attributes.contains(&FieldAttributes::Required))
         .collect();
 
-    optional_fields.iter().for_each(validate_optional_field);
+    for field in optional_fields.iter() {
+        validate_optional_field(field)?;
+
+        let has_none_pair = field
+            .attributes
+            .iter()
+            .any(|attr| matches!(attr, FieldAttributes::NonePair(..)));
+
+        if has_none_pair && field.attributes.contains(&FieldAttributes::Default) {
+            return Err(syn::Error::new_spanned(
+                &field.ident,
+                "#[query(default)] can't be combined with #[query(none_pair(...))]",
+            ));
+        }
+
+        if field.attributes.contains(&FieldAttributes::Unwrap) {
+            if is_result(&field.field.ty) {
+                return Err(syn::Error::new_spanned(
+                    &field.field.ty,
+                    "#[query(unwrap)] can only be used on `Option` fields",
+                ));
+            }
+
+            if has_none_pair || field.attributes.contains(&FieldAttributes::Default) {
+                return Err(syn::Error::new_spanned(
+                    &field.ident,
+                    "#[query(unwrap)] can't be combined with #[query(none_pair(...))] or #[query(default)]",
+                ));
+            }
+        }
+    }
+
+    if sort.is_some() {
+        let ordered = field_descriptions.iter().find(|field| {
+            field
+                .attributes
+                .iter()
+                .any(|attr| matches!(attr, FieldAttributes::Order(_)))
+        });
+
+        if let Some(field) = ordered {
+            return Err(syn::Error::new_spanned(
+                &field.ident,
+                "#[query(order = ...)] can't be combined with #[query(sort)] on the struct",
+            ));
+        }
+    }
+
+    let ordered_fields = ordered_fields(&field_descriptions)?;
+
+    let assignments: TokenStream2 = ordered_fields
+        .iter()
+        .map(|field| field_assignment(field, Method::Query))
+        .collect();
+
+    let unencoded = container_attributes.contains(&ContainerAttributes::Unencoded);
+    let encoded_method = if unencoded {
+        Method::Query
+    } else {
+        Method::Encoded
+    };
 
-    let optional_assignments: TokenStream2 = optional_fields
+    let encoded_assignments: TokenStream2 = ordered_fields
         .iter()
-        .map(|field| {
-            let ident = &field.ident;
-            let name = &field.field_name;
+        .map(|field| field_assignment(field, encoded_method))
+        .collect();
+
+    let max_length = container_attributes.iter().find_map(|attr| match attr {
+        ContainerAttributes::MaxLength(max) => Some(*max),
+        _ => None,
+    });
+
+    // with a limit, the length of the url-encoded query string is summed up as the pairs are pushed
+    let (max_query_length, try_collector, length_check) = match max_length {
+        Some(max) => (
+            Some(quote! {
+                fn max_query_length(&self) -> ::std::option::Option<usize> {
+                    ::std::option::Option::Some(#max)
+                }
+            }),
             quote! {
-                if let Some(val) = &self.#ident {
-                    query_params.push((
-                        #name.to_string(),
-                        val.to_string()
-                    ));
+                let mut query_params = _to_query_params::__private::MeasuredPairs::new();
+            },
+            Some(quote! {
+                let (mut query_params, length) = query_params.finish();
+                if length > #max {
+                    return ::std::result::Result::Err(_to_query_params::QueryParamsError::TooLong {
+                        length,
+                        max: #max,
+                    });
                 }
+            }),
+        ),
+        None => (
+            None,
+            quote! {
+                let mut query_params: ::std::vec::Vec<(String, String)> = ::std::vec::Vec::new();
+            },
+            None,
+        ),
+    };
+    let try_method = Method::Try {
+        measured: max_length.is_some(),
+        encoded: !unencoded,
+    };
+
+    let exclusive_checks: TokenStream2 = exclusive_groups
+        .iter()
+        .map(|group| exclusive_check(group))
+        .collect();
+
+    // sorted pairs can't be streamed, so those structs keep the default over `to_encoded_params`
+    let write_query_string = sort.is_none().then(|| {
+        let write_assignments: TokenStream2 = ordered_fields
+            .iter()
+            .map(|field| {
+                field_assignment(
+                    field,
+                    Method::Write {
+                        encoded: !unencoded,
+                    },
+                )
+            })
+            .collect();
+
+        quote! {
+            fn write_query_string(&self, w: &mut dyn ::std::fmt::Write) -> ::std::fmt::Result {
+                let mut query_params = _to_query_params::__private::QueryWriter::new(w);
+                #write_assignments
+                query_params.finish()
+            }
+        }
+    });
+
+    // only overridden when a field can be moved, otherwise the default clones just the same
+    let into_query_params = field_descriptions.iter().any(moves_value).then(|| {
+        let exclude_flags = field_descriptions
+            .iter()
+            .filter_map(exclude_flag)
+            .map(|(_, declaration)| declaration);
+
+        let owned_assignments: TokenStream2 = ordered_fields
+            .iter()
+            .map(|field| field_assignment(field, Method::Owned))
+            .collect();
+
+        quote! {
+            fn into_query_params(mut self) -> ::std::vec::Vec<(String, String)> {
+                #(#exclude_flags)*
+                let mut query_params: ::std::vec::Vec<(String, String)> = ::std::vec::Vec::new();
+                #owned_assignments
+                #sort
+                query_params
             }
+        }
+    });
+
+    let redacted_assignments: TokenStream2 = ordered_fields
+        .iter()
+        .map(|field| field_assignment(field, Method::Redacted))
+        .collect();
+
+    let redacted = container_attributes
+        .iter()
+        .find_map(|attr| match attr {
+            ContainerAttributes::RedactWith(placeholder) => Some(placeholder.as_str()),
+            _ => None,
         })
+        .unwrap_or("***");
+
+    let try_assignments: TokenStream2 = ordered_fields
+        .iter()
+        .map(|field| field_assignment(field, try_method))
         .collect();
 
-    let optional_encoded_assignments: TokenStream2 = optional_fields
+    let cow_assignments: TokenStream2 = ordered_fields
         .iter()
-        .map(|field| {
-            let ident = &field.ident;
-            let name = &field.field_name;
+        .map(|field| field_assignment(field, Method::Cow))
+        .collect();
+
+    let count_assignments: TokenStream2 = ordered_fields
+        .iter()
+        .map(|field| field_assignment(field, Method::Count))
+        .collect();
+
+    let query_keys = query_keys(&field_descriptions);
+
+    let key_consts = container_attributes
+        .contains(&ContainerAttributes::KeyConsts)
+        .then(|| key_consts(&field_descriptions));
+
+    let inherent_method = container_attributes.iter().find_map(|attr| match attr {
+        ContainerAttributes::Method(name) => Some(quote! {
+            /// Creates the un-encoded query parameters, as in `ToQueryParams::to_query_params`.
+            pub fn #name(&self) -> ::std::vec::Vec<(String, String)> {
+                _to_query_params::ToQueryParams::to_query_params(self)
+            }
+        }),
+        _ => None,
+    });
+
+    #[cfg(feature = "utoipa")]
+    let into_params_impl = container_attributes
+        .contains(&ContainerAttributes::Utoipa)
+        .then(|| utoipa_support::into_params_impl(&ast, &field_descriptions));
+    #[cfg(not(feature = "utoipa"))]
+    let into_params_impl: Option<TokenStream2> = None;
+
+    // only the appended pairs are sorted, leaving what was already in the Vec untouched
+    let (extend_start, extend_sort) = match sort {
+        Some(_) => (
+            Some(quote!(let start = query_params.len();)),
+            Some(quote!(query_params[start..].sort();)),
+        ),
+        None => (None, None),
+    };
+
+    let display_impl = container_attributes
+        .contains(&ContainerAttributes::Display)
+        .then(|| {
             quote! {
-                if let Some(val) = &self.#ident {
-                    query_params.push(
-                        (
-                            ::to_query_params::urlencoding::encode(#name).into_owned(),
-                            ::to_query_params::urlencoding::encode(&val.to_string()).into_owned()
-                        )
-                    );
+                impl #impl_generics ::std::fmt::Display for #ident #ty_generics #where_clause {
+                    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                        f.write_str(&_to_query_params::ToQueryParams::to_query_string(self))
+                    }
                 }
             }
-        })
-        .collect();
+        });
+
+    let into_iterator_impl = container_attributes
+        .contains(&ContainerAttributes::IntoIterator)
+        .then(|| {
+            let mut generics = ast.generics.clone();
+            generics
+                .params
+                .insert(0, syn::parse_quote!('__query_params));
+            let (impl_generics, _, _) = generics.split_for_impl();
+
+            quote! {
+                impl #impl_generics ::std::iter::IntoIterator
+                    for &'__query_params #ident #ty_generics #where_clause
+                {
+                    type Item = (String, String);
+                    type IntoIter = ::std::vec::IntoIter<(String, String)>;
+
+                    fn into_iter(self) -> Self::IntoIter {
+                        _to_query_params::ToQueryParams::to_query_params(self).into_iter()
+                    }
+                }
+            }
+        });
 
+    // the impls are wrapped in an anonymous const so the crate can be imported under a fixed name,
+    // without requiring the trait or the crate itself to be in scope at the derive site
     let trait_impl = quote! {
-        #[allow(dead_code)]
-        impl ToQueryParams for #ident {
-            fn to_query_params(&self) -> ::std::vec::Vec<(String, String)> {
-                #vec_definition
-                #optional_assignments
-                query_params
+        const _: () = {
+            use #krate as _to_query_params;
+
+            /// The value emitted in place of `sensitive` fields by `to_redacted_params`.
+            #[allow(dead_code)]
+            const REDACTED: &str = #redacted;
+
+            #[allow(dead_code)]
+            impl #impl_generics #ident #ty_generics #where_clause {
+                /// The un-encoded keys that can be emitted as query parameters, in declaration order.
+                pub const QUERY_KEYS: &'static [&'static str] = &[#(#query_keys),*];
+
+                #key_consts
+
+                #inherent_method
             }
 
-            fn to_encoded_params(&self) -> ::std::vec::Vec<(String, String)> {
-                #vec_encoded_definition
-                #optional_encoded_assignments
-                query_params
+            impl #impl_generics _to_query_params::QueryParamsMeta for #ident #ty_generics #where_clause {
+                fn param_names() -> &'static [&'static str] {
+                    Self::QUERY_KEYS
+                }
             }
-        }
-    };
 
-    trait_impl.into()
-}
+            impl #impl_generics _to_query_params::TryToQueryParams for #ident #ty_generics #where_clause {
+                fn try_to_query_params(
+                    &self,
+                ) -> ::std::result::Result<
+                    ::std::vec::Vec<(String, String)>,
+                    _to_query_params::QueryParamsError,
+                > {
+                    #exclusive_checks
+                    #try_collector
+                    #try_assignments
+                    #length_check
+                    #sort
+                    ::std::result::Result::Ok(query_params)
+                }
+            }
 
-fn map_field_to_description(field: &Field) -> FieldDescription {
-    let attributes = field
-        .attrs
-        .iter()
-        .flat_map(parse_query_attributes)
-        .collect::<HashSet<FieldAttributes>>();
+            #[allow(dead_code)]
+            impl #impl_generics _to_query_params::ToQueryParams for #ident #ty_generics #where_clause {
+                fn extend_params(&self, query_params: &mut ::std::vec::Vec<(String, String)>) {
+                    #extend_start
+                    #assignments
+                    #extend_sort
+                }
 
-    let mut desc = FieldDescription {
-        field,
-        field_name: field.ident.as_ref().unwrap().to_string(),
-        ident: field.ident.clone().unwrap(),
-        attributes,
+                fn to_query_params(&self) -> ::std::vec::Vec<(String, String)> {
+                    let mut query_params: ::std::vec::Vec<(String, String)> = ::std::vec::Vec::new();
+                    _to_query_params::ToQueryParams::extend_params(self, &mut query_params);
+                    query_params
+                }
+
+                // bindings and defaults are kept as in `extend_params`, even where only counted
+                #[allow(unused_variables, unused_assignments)]
+                fn params_len(&self) -> usize {
+                    let mut count = 0;
+                    #count_assignments
+                    count
+                }
+
+                // sensitive fields bind their values without reading them
+                #[allow(unused_variables)]
+                fn to_redacted_params(&self) -> ::std::vec::Vec<(String, String)> {
+                    let mut query_params: ::std::vec::Vec<(String, String)> = ::std::vec::Vec::new();
+                    #redacted_assignments
+                    #sort
+                    query_params
+                }
+
+                fn to_encoded_params(&self) -> ::std::vec::Vec<(String, String)> {
+                    let mut query_params: ::std::vec::Vec<(String, String)> = ::std::vec::Vec::new();
+                    #encoded_assignments
+                    #sort
+                    query_params
+                }
+
+                #write_query_string
+
+                fn to_query_params_cow(
+                    &self,
+                ) -> ::std::vec::Vec<(
+                    ::std::borrow::Cow<'static, str>,
+                    ::std::borrow::Cow<'_, str>,
+                )> {
+                    let mut query_params: ::std::vec::Vec<(
+                        ::std::borrow::Cow<'static, str>,
+                        ::std::borrow::Cow<'_, str>,
+                    )> = ::std::vec::Vec::new();
+                    #cow_assignments
+                    #sort
+                    query_params
+                }
+
+                #into_query_params
+
+                #max_query_length
+            }
+
+            #display_impl
+
+            #into_iterator_impl
+
+            #into_params_impl
+        };
     };
 
-    let name = name_from_field_description(&desc);
-    desc.field_name = name;
-    desc
+    Ok(trait_impl)
 }
 
-fn name_from_field_description(field: &FieldDescription) -> String {
-    let mut name = field.ident.to_string();
-    for attribute in field.attributes.iter() {
-        if let FieldAttributes::Rename(rename) = attribute {
-            name = (*rename).clone();
+/// Derives the value mapping for an enum of unit variants: an `as_query_value` method returning
+/// each variant's name, or its `#[query(rename = "...")]`, and a `Display` impl using it, so fields
+/// of the enum's type are emitted with those names.
+fn derive_enum_value(ast: &DeriveInput, data: &DataEnum) -> syn::Result<TokenStream2> {
+    let ident = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let mut arms = Vec::new();
+    for variant in data.variants.iter() {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "QueryParams can only be derived for enums with unit variants",
+            ));
         }
-    }
 
-    name
-}
+        let mut name = variant.ident.unraw().to_string();
+        for attr in variant.attrs.iter() {
+            for attribute in parse_query_attributes(attr)? {
+                if let FieldAttributes::Rename(rename) = attribute {
+                    name = rename;
+                }
+            }
+        }
 
-fn parse_query_attributes(attr: &Attribute) -> Vec<FieldAttributes> {
-    let mut attrs = Vec::new();
+        let variant_ident = &variant.ident;
+        arms.push(quote!(Self::#variant_ident => #name));
+    }
 
-    if attr.path().is_ident("query") {
-        attr.parse_nested_meta(|m| {
-            if m.path.is_ident("required") {
-                attrs.push(FieldAttributes::Required);
+    Ok(quote! {
+        #[allow(dead_code)]
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// The value emitted for this variant when used as a query parameter.
+            pub fn as_query_value(&self) -> &'static str {
+                match *self {
+                    #(#arms,)*
+                }
             }
+        }
 
-            if m.path.is_ident("exclude") {
-                attrs.push(FieldAttributes::Excluded);
+        impl #impl_generics ::std::fmt::Display for #ident #ty_generics #where_clause {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.write_str(self.as_query_value())
             }
+        }
+    })
+}
 
-            if m.path.is_ident("rename") {
-                let value = m.value().unwrap();
-                let rename: LitStr = value.parse().unwrap();
+/// Returns the fields in the order their pairs are pushed: fields with `#[query(order = N)]` first,
+/// by ascending `N`, followed by the remaining fields in declaration order.
+fn ordered_fields<'a, 'f>(
+    field_descriptions: &'a [FieldDescription<'f>],
+) -> syn::Result<Vec<&'a FieldDescription<'f>>> {
+    let order_of = |field: &FieldDescription| {
+        field.attributes.iter().find_map(|attr| match attr {
+            FieldAttributes::Order(order) => Some(*order),
+            _ => None,
+        })
+    };
 
-                attrs.push(FieldAttributes::Rename(rename.value()));
+    let mut seen: Vec<(u32, &FieldDescription)> = Vec::new();
+    for field in field_descriptions {
+        if let Some(order) = order_of(field) {
+            if let Some((_, other)) = seen.iter().find(|(seen_order, _)| *seen_order == order) {
+                return Err(syn::Error::new_spanned(
+                    &field.ident,
+                    format!(
+                        "duplicate `order = {order}`, also used by field `{}`",
+                        other.ident
+                    ),
+                ));
             }
 
-            Ok(())
-        })
-        .expect("Unsupported attribute found in #[query(...)] attribute");
+            seen.push((order, field));
+        }
     }
 
-    attrs
+    let mut ordered: Vec<&FieldDescription> = field_descriptions.iter().collect();
+    // the sort is stable, so unordered fields keep their declaration order
+    ordered.sort_by_key(|field| order_of(field).map_or((1, 0), |order| (0, order)));
+
+    Ok(ordered)
 }
 
-fn validate_optional_field(field_desc: &&FieldDescription) {
-    if let Type::Path(type_path) = &field_desc.field.ty {
-        if !(type_path.qself.is_none() && path_is_option(&type_path.path)) {
-            panic!("Non-optional types must be marked with #[query(required)] attribute")
+/// Makes optional fields emit their key with an empty value when `None`, as if they were marked
+/// `#[query(none_pair("key" = ""))]`, unless they already handle `None` themselves, or their keys
+/// are only known at runtime.
+fn keep_none_fields(mut field_descriptions: Vec<FieldDescription>) -> Vec<FieldDescription> {
+    for field in field_descriptions.iter_mut() {
+        let handles_none = field.attributes.iter().any(|attr| {
+            matches!(
+                attr,
+                FieldAttributes::Required
+                    | FieldAttributes::Default
+                    | FieldAttributes::Unwrap
+                    | FieldAttributes::NonePair(..)
+            )
+        });
+
+        if !handles_none && !has_dynamic_keys(field) {
+            let key = field.field_name.clone();
+            field
+                .attributes
+                .push(FieldAttributes::NonePair(key, String::new()));
         }
     }
+
+    field_descriptions
 }
 
-fn path_is_option(path: &Path) -> bool {
-    path.leading_colon.is_none()
-        && path.segments.len() == 1
-        && path.segments.iter().next().unwrap().ident == "Option"
+/// Resolves the field names of an `exclusive` group to the struct's fields.
+fn exclusive_group<'a, 'f>(
+    names: &[SpannedName],
+    field_descriptions: &'a [FieldDescription<'f>],
+) -> syn::Result<Vec<&'a FieldDescription<'f>>> {
+    names
+        .iter()
+        .map(|name| {
+            field_descriptions
+                .iter()
+                .find(|field| field.ident.unraw() == name.name)
+                .ok_or_else(|| {
+                    syn::Error::new(
+                        name.span,
+                        format!(
+                            "no field named `{}`, #[query(exclusive(...))] takes the names of non-excluded fields",
+                            name.name
+                        ),
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Produces the statements checking that at most one field of an `exclusive` group is set, where
+/// required fields are always set and optional fields are set when `Some` or `Ok`. A failed check
+/// returns a `QueryParamsError::Exclusive`.
+fn exclusive_check(group: &[&FieldDescription]) -> TokenStream2 {
+    let names = group.iter().map(|field| field.ident.unraw().to_string());
+    let present = group.iter().map(|field| {
+        let ident = &field.ident;
+        if field.attributes.contains(&FieldAttributes::Required) {
+            quote!(true)
+        } else if is_result(&field.field.ty) {
+            quote!(::std::matches!(self.#ident, Ok(_)))
+        } else {
+            quote!(::std::matches!(self.#ident, Some(_)))
+        }
+    });
+
+    let error = quote! {
+        _to_query_params::QueryParamsError::Exclusive {
+            fields: names
+                .iter()
+                .zip(present)
+                .filter_map(|(name, present)| present.then_some(*name))
+                .collect(),
+        }
+    };
+
+    quote! {
+        {
+            let names = [#(#names),*];
+            let present = [#(#present),*];
+            if present.iter().filter(|present| **present).count() > 1 {
+                return ::std::result::Result::Err(#error);
+            }
+        }
+    }
+}
+
+/// Collects the distinct keys the struct can emit, in declaration order, including the keys of
+/// `none_pair` attributes. Fields whose keys are only known at runtime are left out.
+fn query_keys(field_descriptions: &[FieldDescription]) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+
+    for field in field_descriptions {
+        if has_dynamic_keys(field) {
+            continue;
+        }
+
+        let none_keys = field.attributes.iter().filter_map(|attr| match attr {
+            FieldAttributes::NonePair(key, _) => Some(key),
+            _ => None,
+        });
+
+        for key in std::iter::once(&field.field_name)
+            .chain(aliases(field))
+            .chain(none_keys)
+        {
+            if !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+    }
+
+    keys
+}
+
+/// Declares a `KEY_<FIELD>` constant holding the un-encoded key of each field, skipping fields
+/// whose keys are only known at runtime.
+fn key_consts(field_descriptions: &[FieldDescription]) -> TokenStream2 {
+    field_descriptions
+        .iter()
+        .filter(|field| !has_dynamic_keys(field))
+        .map(|field| {
+            let name = field.ident.unraw().to_string().to_uppercase();
+            let const_ident = format_ident!("KEY_{}", name, span = field.ident.span());
+            let key = &field.field_name;
+            let doc = format!("The key of the `{}` field.", field.ident.unraw());
+
+            quote! {
+                #[doc = #doc]
+                pub const #const_ident: &'static str = #key;
+            }
+        })
+        .collect()
+}
+
+fn map_field_to_description(field: &Field) -> syn::Result<FieldDescription<'_>> {
+    let mut parsed = Vec::new();
+    for attr in field.attrs.iter() {
+        parsed.extend(parse_query_attributes(attr)?);
+    }
+
+    // transforms are parsed upfront, since they're applied to every value the field emits
+    let transforms = parsed
+        .iter()
+        .filter_map(|attr| match attr {
+            FieldAttributes::Transform(path) => {
+                Some(syn::parse_str(path).expect("transform paths are validated during parsing"))
+            }
+            _ => None,
+        })
+        .collect();
+
+    // attributes are kept in the order they're declared, including repeated ones like `alias`
+    let mut attributes = parsed;
+
+    let nests = attributes
+        .iter()
+        .filter(|attr| {
+            matches!(
+                attr,
+                FieldAttributes::Nested | FieldAttributes::Indexed | FieldAttributes::Flatten
+            )
+        })
+        .count();
+    if nests > 1 {
+        return Err(syn::Error::new_spanned(
+            &field.ident,
+            "only one of #[query(nested)], #[query(indexed)] or #[query(flatten)] can be used on a field",
+        ));
+    }
+
+    // an empty map emits nothing, so a map doesn't need to be marked as required
+    if attributes.contains(&FieldAttributes::Flatten)
+        && !is_option(&field.ty)
+        && !attributes.contains(&FieldAttributes::Required)
+    {
+        attributes.push(FieldAttributes::Required);
+    }
+
+    let ty = formatted_type(&field.ty);
+    if attributes.contains(&FieldAttributes::BracketIpv6)
+        && !(type_is(ty, "Ipv6Addr") || type_is(ty, "IpAddr"))
+    {
+        return Err(syn::Error::new_spanned(
+            &field.ty,
+            "#[query(bracket_ipv6)] can only be used on `Ipv6Addr` or `IpAddr` fields",
+        ));
+    }
+
+    let has_bool_format = attributes
+        .iter()
+        .any(|attr| matches!(attr, FieldAttributes::BoolFormat(..)));
+    if has_bool_format && !type_is(ty, "bool") {
+        return Err(syn::Error::new_spanned(
+            &field.ty,
+            "#[query(bool_format = \"...\")] can only be used on `bool` fields",
+        ));
+    }
+
+    let mut desc = FieldDescription {
+        field,
+        field_name: field.ident.as_ref().unwrap().unraw().to_string(),
+        ident: field.ident.clone().unwrap(),
+        attributes,
+        transforms,
+    };
+
+    if !aliases(&desc).is_empty() && has_dynamic_keys(&desc) {
+        return Err(syn::Error::new_spanned(
+            &field.ident,
+            "#[query(alias = \"...\")] can't be used on `nested`, `indexed`, `flatten` or `value_object` fields",
+        ));
+    }
+
+    let name = name_from_field_description(&desc);
+    desc.field_name = name;
+    Ok(desc)
+}
+
+fn name_from_field_description(field: &FieldDescription) -> String {
+    let mut name = field.ident.unraw().to_string();
+    for attribute in field.attributes.iter() {
+        if let FieldAttributes::Rename(rename) = attribute {
+            name = (*rename).clone();
+        }
+    }
+
+    name
+}
+
+/// The keys accepted inside `#[query(...)]` on the struct itself.
+const CONTAINER_KEYS: &[&str] = &[
+    "sort",
+    "sorted",
+    "allow_duplicate_keys",
+    "crate",
+    "encode",
+    "rename_all",
+    "skip_none",
+    "display",
+    "into_iterator",
+    "key_consts",
+    "exclusive",
+    "max_length",
+    "redact_with",
+    "method",
+];
+
+/// The keys accepted inside `#[query(...)]` on a field.
+const FIELD_KEYS: &[&str] = &[
+    "required",
+    "exclude",
+    "rename",
+    "alias",
+    "raw",
+    "nested",
+    "indexed",
+    "flatten",
+    "bracket_ipv6",
+    "bool_format",
+    "order",
+    "encode",
+    "format",
+    "skip_if",
+    "skip_field_if",
+    "exclude_if",
+    "when",
+    "sensitive",
+    "none_pair",
+    "transform",
+    "with_try",
+    "min",
+    "max",
+    "non_empty",
+    "epoch",
+    "duration",
+    "default",
+    "unwrap",
+];
+
+/// The keys accepted inside `#[query(...)]` on a field that depend on a feature, with the name of
+/// the feature and whether it's enabled.
+const FEATURE_FIELD_KEYS: &[(&str, &str, bool)] = &[
+    ("uuid", "uuid", cfg!(feature = "uuid")),
+    ("base64", "base64", cfg!(feature = "base64")),
+    ("json", "serde_json", cfg!(feature = "serde_json")),
+    ("value_object", "serde_json", cfg!(feature = "serde_json")),
+    ("datetime_format", "chrono", cfg!(feature = "chrono")),
+    ("time_format", "time", cfg!(feature = "time")),
+    ("pattern", "regex", cfg!(feature = "regex")),
+];
+
+/// The keys accepted inside `#[query(...)]` on the struct that depend on a feature, with the name
+/// of the feature and whether it's enabled.
+const FEATURE_CONTAINER_KEYS: &[(&str, &str, bool)] =
+    &[("utoipa", "utoipa", cfg!(feature = "utoipa"))];
+
+/// Rejects a key inside `#[query(...)]` that isn't one of `keys` or an enabled `feature_keys`, so
+/// typos aren't silently ignored.
+fn validate_key(
+    m: &ParseNestedMeta,
+    keys: &[&str],
+    feature_keys: &[(&str, &str, bool)],
+) -> syn::Result<()> {
+    if keys.iter().any(|key| m.path.is_ident(key)) {
+        return Ok(());
+    }
+
+    for (key, feature, enabled) in feature_keys {
+        if m.path.is_ident(key) {
+            if *enabled {
+                return Ok(());
+            }
+
+            return Err(m.error(format!(
+                "`{key}` requires the `{feature}` feature of to-query-params"
+            )));
+        }
+    }
+
+    // feature keys are always listed, so the message doesn't depend on the enabled features
+    let expected = keys
+        .iter()
+        .chain(feature_keys.iter().map(|(key, _, _)| key))
+        .copied()
+        .collect::<Vec<&str>>()
+        .join(", ");
+
+    let path = &m.path;
+    Err(m.error(format!(
+        "unknown query attribute `{}`; expected one of {expected}",
+        quote!(#path)
+    )))
+}
+
+fn parse_container_attributes(attr: &Attribute) -> syn::Result<Vec<ContainerAttributes>> {
+    let mut attrs = Vec::new();
+
+    if attr.path().is_ident("query") {
+        attr.parse_nested_meta(|m| {
+            validate_key(&m, CONTAINER_KEYS, FEATURE_CONTAINER_KEYS)?;
+
+            if m.path.is_ident("sort") || m.path.is_ident("sorted") {
+                attrs.push(ContainerAttributes::Sort);
+            }
+
+            if m.path.is_ident("allow_duplicate_keys") {
+                attrs.push(ContainerAttributes::AllowDuplicateKeys);
+            }
+
+            if m.path.is_ident("crate") {
+                let path = parse_string_value(&m, "crate")?;
+                path.parse::<Path>().map_err(|_| {
+                    syn::Error::new_spanned(&path, "`crate` must be a path to this crate")
+                })?;
+
+                attrs.push(ContainerAttributes::Crate(path.value()));
+            }
+
+            if m.path.is_ident("rename_all") {
+                let name = parse_string_value(&m, "rename_all")?;
+
+                let rule = RenameRule::from_name(&name.value()).ok_or_else(|| {
+                    syn::Error::new_spanned(
+                        &name,
+                        format!(
+                            "unsupported rename_all rule, expected one of {}",
+                            RenameRule::NAMES.join(", ")
+                        ),
+                    )
+                })?;
+
+                attrs.push(ContainerAttributes::RenameAll(rule));
+            }
+
+            if m.path.is_ident("into_iterator") {
+                attrs.push(ContainerAttributes::IntoIterator);
+            }
+
+            if m.path.is_ident("exclusive") {
+                let content;
+                syn::parenthesized!(content in m.input);
+
+                let names =
+                    content.parse_terminated(<LitStr as syn::parse::Parse>::parse, Token![,])?;
+                if names.len() < 2 {
+                    return Err(m.error("#[query(exclusive(...))] needs at least two field names"));
+                }
+
+                attrs.push(ContainerAttributes::Exclusive(
+                    names
+                        .iter()
+                        .map(|name| SpannedName {
+                            name: name.value(),
+                            span: name.span(),
+                        })
+                        .collect(),
+                ));
+            }
+
+            if m.path.is_ident("redact_with") {
+                let placeholder = parse_string_value(&m, "redact_with")?;
+
+                attrs.push(ContainerAttributes::RedactWith(placeholder.value()));
+            }
+
+            if m.path.is_ident("max_length") {
+                let message = "expected an integer value, e.g. `max_length = 8192`";
+
+                if !m.input.peek(Token![=]) {
+                    return Err(m.error(message));
+                }
+
+                let max = m
+                    .value()?
+                    .parse::<LitInt>()
+                    .and_then(|lit| lit.base10_parse::<usize>())
+                    .map_err(|err| syn::Error::new(err.span(), message))?;
+
+                attrs.push(ContainerAttributes::MaxLength(max));
+            }
+
+            if m.path.is_ident("method") {
+                let name = parse_string_value(&m, "method")?;
+                // keywords are rejected, while raw identifiers like `r#type` are kept as-is
+                let method = name.parse::<Ident>().map_err(|_| {
+                    syn::Error::new_spanned(&name, "`method` must be a valid method name")
+                })?;
+
+                attrs.push(ContainerAttributes::Method(method));
+            }
+
+            #[cfg(feature = "utoipa")]
+            if m.path.is_ident("utoipa") {
+                attrs.push(ContainerAttributes::Utoipa);
+            }
+
+            if m.path.is_ident("key_consts") {
+                attrs.push(ContainerAttributes::KeyConsts);
+            }
+
+            if m.path.is_ident("display") {
+                attrs.push(ContainerAttributes::Display);
+            }
+
+            if m.path.is_ident("skip_none") && !parse_bool_value(&m, "skip_none")?.value() {
+                attrs.push(ContainerAttributes::KeepNone);
+            }
+
+            if m.path.is_ident("encode") && !parse_bool_value(&m, "encode")?.value() {
+                attrs.push(ContainerAttributes::Unencoded);
+            }
+
+            Ok(())
+        })?;
+    }
+
+    Ok(attrs)
+}
+
+fn parse_query_attributes(attr: &Attribute) -> syn::Result<Vec<FieldAttributes>> {
+    let mut attrs = Vec::new();
+
+    if attr.path().is_ident("query") {
+        attr.parse_nested_meta(|m| {
+            validate_key(&m, FIELD_KEYS, FEATURE_FIELD_KEYS)?;
+
+            if m.path.is_ident("required") {
+                attrs.push(FieldAttributes::Required);
+            }
+
+            if m.path.is_ident("exclude") {
+                attrs.push(FieldAttributes::Excluded);
+            }
+
+            if m.path.is_ident("rename") {
+                let rename = parse_string_value(&m, "rename")?;
+
+                attrs.push(FieldAttributes::Rename(rename.value()));
+            }
+
+            if m.path.is_ident("alias") {
+                let alias = parse_string_value(&m, "alias")?;
+
+                attrs.push(FieldAttributes::Alias(alias.value()));
+            }
+
+            if m.path.is_ident("raw") {
+                attrs.push(FieldAttributes::Raw);
+            }
+
+            if m.path.is_ident("default") {
+                attrs.push(FieldAttributes::Default);
+            }
+
+            if m.path.is_ident("unwrap") {
+                attrs.push(FieldAttributes::Unwrap);
+            }
+
+            if m.path.is_ident("nested") {
+                attrs.push(FieldAttributes::Nested);
+            }
+
+            if m.path.is_ident("indexed") {
+                attrs.push(FieldAttributes::Indexed);
+            }
+
+            if m.path.is_ident("flatten") {
+                attrs.push(FieldAttributes::Flatten);
+            }
+
+            if m.path.is_ident("bracket_ipv6") {
+                attrs.push(FieldAttributes::BracketIpv6);
+            }
+
+            if m.path.is_ident("bool_format") {
+                let spec = parse_string_value(&m, "bool_format")?;
+
+                let (truthy, falsy) = spec
+                    .value()
+                    .split_once('/')
+                    .filter(|(truthy, falsy)| {
+                        !truthy.is_empty() && !falsy.is_empty() && !falsy.contains('/')
+                    })
+                    .map(|(truthy, falsy)| (truthy.to_string(), falsy.to_string()))
+                    .ok_or_else(|| {
+                        syn::Error::new_spanned(
+                            &spec,
+                            "expected the true and false values separated by `/`, e.g. `bool_format = \"1/0\"`",
+                        )
+                    })?;
+
+                attrs.push(FieldAttributes::BoolFormat(truthy, falsy));
+            }
+
+            if m.path.is_ident("order") {
+                let message = "expected an integer value, e.g. `order = 1`";
+
+                if !m.input.peek(Token![=]) {
+                    return Err(m.error(message));
+                }
+
+                let order = m
+                    .value()?
+                    .parse::<LitInt>()
+                    .and_then(|lit| lit.base10_parse::<u32>())
+                    .map_err(|err| syn::Error::new(err.span(), message))?;
+
+                attrs.push(FieldAttributes::Order(order));
+            }
+
+            if m.path.is_ident("encode") {
+                let mode = parse_string_value(&m, "encode")?;
+
+                if !["both", "value_only", "key_only", "none", "component", "form"]
+                    .contains(&mode.value().as_str())
+                {
+                    return Err(syn::Error::new_spanned(
+                        mode,
+                        "unsupported encode mode, expected \"both\", \"value_only\", \"key_only\", \"none\", \"component\" or \"form\"",
+                    ));
+                }
+
+                attrs.push(FieldAttributes::Encode(mode.value()));
+            }
+
+            if m.path.is_ident("format") {
+                let format = parse_string_value(&m, "format")?;
+                validate_format_string(&format)?;
+
+                attrs.push(FieldAttributes::Format(format.value()));
+            }
+
+            if m.path.is_ident("skip_if") {
+                let predicate = parse_predicate_value(&m, "skip_if")?;
+
+                attrs.push(FieldAttributes::SkipIf(predicate.value()));
+            }
+
+            if m.path.is_ident("skip_field_if") {
+                let predicate = parse_predicate_value(&m, "skip_field_if")?;
+
+                attrs.push(FieldAttributes::SkipFieldIf(predicate.value()));
+            }
+
+            if m.path.is_ident("exclude_if") {
+                let predicate = parse_predicate_value(&m, "exclude_if")?;
+
+                attrs.push(FieldAttributes::ExcludeIf(predicate.value()));
+            }
+
+            if m.path.is_ident("sensitive") {
+                attrs.push(FieldAttributes::Sensitive);
+            }
+
+            if m.path.is_ident("when") {
+                let predicate = parse_predicate_value(&m, "when")?;
+
+                attrs.push(FieldAttributes::When(predicate.value()));
+            }
+
+            if m.path.is_ident("none_pair") {
+                let content;
+                syn::parenthesized!(content in m.input);
+
+                let key: LitStr = content.parse()?;
+                content.parse::<Token![=]>()?;
+                let value: LitStr = content.parse()?;
+
+                attrs.push(FieldAttributes::NonePair(key.value(), value.value()));
+            }
+
+            if m.path.is_ident("transform") {
+                let transform = parse_predicate_value(&m, "transform")?;
+
+                attrs.push(FieldAttributes::Transform(transform.value()));
+            }
+
+            if m.path.is_ident("with_try") {
+                let function = parse_predicate_value(&m, "with_try")?;
+
+                attrs.push(FieldAttributes::WithTry(function.value()));
+            }
+
+            #[cfg(feature = "uuid")]
+            if m.path.is_ident("uuid") {
+                let format = parse_string_value(&m, "uuid")?;
+
+                if !["hyphenated", "simple", "urn"].contains(&format.value().as_str()) {
+                    return Err(syn::Error::new_spanned(
+                        format,
+                        "unsupported uuid format, expected \"hyphenated\", \"simple\" or \"urn\"",
+                    ));
+                }
+
+                attrs.push(FieldAttributes::Uuid(format.value()));
+            }
+
+            #[cfg(feature = "base64")]
+            if m.path.is_ident("base64") {
+                attrs.push(FieldAttributes::Base64);
+            }
+
+            #[cfg(feature = "serde_json")]
+            if m.path.is_ident("json") {
+                attrs.push(FieldAttributes::Json);
+            }
+
+            #[cfg(feature = "serde_json")]
+            if m.path.is_ident("value_object") {
+                attrs.push(FieldAttributes::ValueObject);
+            }
+
+            #[cfg(feature = "chrono")]
+            if m.path.is_ident("datetime_format") {
+                let format = parse_string_value(&m, "datetime_format")?;
+
+                attrs.push(FieldAttributes::DatetimeFormat(format.value()));
+            }
+
+            #[cfg(feature = "time")]
+            if m.path.is_ident("time_format") {
+                let format = parse_string_value(&m, "time_format")?;
+
+                attrs.push(FieldAttributes::TimeFormat(format.value()));
+            }
+
+            #[cfg(feature = "regex")]
+            if m.path.is_ident("pattern") {
+                let pattern = parse_string_value(&m, "pattern")?;
+                regex::Regex::new(&pattern.value()).map_err(|error| {
+                    syn::Error::new_spanned(&pattern, format!("invalid pattern: {error}"))
+                })?;
+
+                attrs.push(FieldAttributes::Pattern(pattern.value()));
+            }
+
+            if m.path.is_ident("min") {
+                attrs.push(FieldAttributes::Min(parse_number_value(&m, "min")?));
+            }
+
+            if m.path.is_ident("max") {
+                attrs.push(FieldAttributes::Max(parse_number_value(&m, "max")?));
+            }
+
+            if m.path.is_ident("non_empty") {
+                attrs.push(FieldAttributes::NonEmpty);
+            }
+
+            if m.path.is_ident("epoch") {
+                let unit = parse_string_value(&m, "epoch")?;
+
+                if !["secs", "millis"].contains(&unit.value().as_str()) {
+                    return Err(syn::Error::new_spanned(
+                        unit,
+                        "unsupported epoch unit, expected \"secs\" or \"millis\"",
+                    ));
+                }
+
+                attrs.push(FieldAttributes::Epoch(unit.value()));
+            }
+
+            if m.path.is_ident("duration") {
+                let unit = parse_string_value(&m, "duration")?;
+
+                if !["secs", "millis"].contains(&unit.value().as_str()) {
+                    return Err(syn::Error::new_spanned(
+                        unit,
+                        "unsupported duration unit, expected \"secs\" or \"millis\"",
+                    ));
+                }
+
+                attrs.push(FieldAttributes::Duration(unit.value()));
+            }
+
+            Ok(())
+        })?;
+    }
+
+    Ok(attrs)
+}
+
+/// Parses the `= "..."` following `name` in a `#[query(...)]` attribute.
+fn parse_string_value(m: &ParseNestedMeta, name: &str) -> syn::Result<LitStr> {
+    let message = format!("expected a string value, e.g. `{name} = \"...\"`");
+
+    if !m.input.peek(Token![=]) {
+        return Err(m.error(message));
+    }
+
+    m.value()?
+        .parse::<LitStr>()
+        .map_err(|err| syn::Error::new(err.span(), message))
+}
+
+/// Parses the `= true` or `= false` following `name` in a `#[query(...)]` attribute.
+fn parse_bool_value(m: &ParseNestedMeta, name: &str) -> syn::Result<LitBool> {
+    let message = format!("expected a boolean value, e.g. `{name} = false`");
+
+    if !m.input.peek(Token![=]) {
+        return Err(m.error(message));
+    }
+
+    m.value()?
+        .parse::<LitBool>()
+        .map_err(|err| syn::Error::new(err.span(), message))
+}
+
+/// Parses the `= N` following `name` in a `#[query(...)]` attribute, where `N` is an integer or
+/// float literal, optionally negated, returning it as written.
+fn parse_number_value(m: &ParseNestedMeta, name: &str) -> syn::Result<String> {
+    let message = format!("expected a number, e.g. `{name} = 1`");
+
+    if !m.input.peek(Token![=]) {
+        return Err(m.error(message));
+    }
+
+    let input = m.value()?;
+    let negated = input.parse::<Option<Token![-]>>()?.is_some();
+    let number = match input.parse::<Lit>() {
+        Ok(Lit::Int(int)) => int.to_string(),
+        Ok(Lit::Float(float)) => float.to_string(),
+        Ok(other) => return Err(syn::Error::new_spanned(other, message)),
+        Err(err) => return Err(syn::Error::new(err.span(), message)),
+    };
+
+    Ok(if negated {
+        format!("-{number}")
+    } else {
+        number
+    })
+}
+
+/// Checks that a format string has exactly one positional placeholder, like `{}` or `{:.2}`.
+fn validate_format_string(format: &LitStr) -> syn::Result<()> {
+    let value = format.value();
+    let mut chars = value.chars().peekable();
+    let mut placeholders = Vec::new();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                for ch in chars.by_ref() {
+                    if ch == '}' {
+                        break;
+                    }
+                    placeholder.push(ch);
+                }
+                placeholders.push(placeholder);
+            }
+            _ => {}
+        }
+    }
+
+    let positional = |placeholder: &String| {
+        let argument = placeholder.split(':').next().unwrap_or_default();
+        argument.is_empty() || argument == "0"
+    };
+
+    if placeholders.len() != 1 || !placeholders.iter().all(positional) {
+        return Err(syn::Error::new_spanned(
+            format,
+            "format must contain exactly one positional placeholder, e.g. \"{}\" or \"{:.2}\"",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Parses the `= "path::to::function"` following `name` in a `#[query(...)]` attribute.
+fn parse_predicate_value(m: &ParseNestedMeta, name: &str) -> syn::Result<LitStr> {
+    let predicate = parse_string_value(m, name)?;
+
+    predicate.parse::<ExprPath>().map_err(|_| {
+        syn::Error::new_spanned(&predicate, format!("`{name}` must be a path to a function"))
+    })?;
+
+    Ok(predicate)
+}
+
+/// The generated method that field assignments are produced for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Method {
+    /// `to_query_params`, borrowing the struct.
+    Query,
+    /// `to_encoded_params`, borrowing the struct and url-encoding the pairs.
+    Encoded,
+    /// `into_query_params`, moving `String` fields out of the struct.
+    Owned,
+    /// `params_len`, counting the pairs into `count` instead of pushing them.
+    Count,
+    /// `try_to_query_params`, returning serialization errors instead of panicking. If `measured`,
+    /// the pairs are pushed onto a `MeasuredPairs` instead, which also sums up the length of the
+    /// query string, url-encoded unless `encoded` is unset.
+    Try { measured: bool, encoded: bool },
+    /// `to_redacted_params`, replacing the values of `sensitive` fields with `REDACTED`.
+    Redacted,
+    /// `to_query_params_cow`, borrowing keys and `String` values instead of allocating them.
+    Cow,
+    /// `write_query_string`, writing the pairs into a `QueryWriter` and url-encoding them as
+    /// they're written, unless `encoded` is unset.
+    Write { encoded: bool },
+}
+
+/// Produces the statements pushing a field's pair(s) onto `query_params` for `method`.
+///
+/// For `Method::Owned`, `String` fields are moved out of `self`, so `exclude_if` and `when`
+/// predicates read the flags declared by `exclude_flags` instead.
+fn field_assignment(field: &FieldDescription, method: Method) -> TokenStream2 {
+    let ident = &field.ident;
+    let encoding = Encoding::for_field(field, method);
+    let required = field.attributes.contains(&FieldAttributes::Required);
+    let moved = method == Method::Owned && moves_value(field);
+
+    let reference = if required {
+        quote!(&self.#ident)
+    } else if moved {
+        quote!(&*val)
+    } else {
+        quote!(val)
+    };
+
+    let mut assignment = if moved {
+        let name = &field.field_name;
+        let target = if required {
+            quote!(&mut self.#ident)
+        } else {
+            quote!(val)
+        };
+        let pair = pair_expression(quote!(#name), quote!(::std::mem::take(#target)), encoding);
+
+        quote! {
+            query_params.push(#pair);
+        }
+    } else if method == Method::Count {
+        field_count(field, reference.clone())
+    } else {
+        field_push(field, reference.clone(), encoding, method)
+    };
+
+    // pairs are only counted, so their values are never checked, and redacted pairs are logged, so
+    // they mustn't panic
+    if !matches!(method, Method::Count | Method::Redacted) {
+        let checks = validation_checks(
+            field,
+            reference.clone(),
+            matches!(method, Method::Try { .. }),
+        );
+        assignment = quote! {
+            #checks
+            #assignment
+        };
+    }
+
+    for predicate in predicate_paths(field, |attr| match attr {
+        FieldAttributes::SkipIf(path) => Some(path),
+        _ => None,
+    }) {
+        assignment = quote! {
+            if !(#predicate)(#reference) {
+                #assignment
+            }
+        };
+    }
+
+    if !required {
+        let none_assignment = field
+            .attributes
+            .iter()
+            .find_map(|attr| match attr {
+                FieldAttributes::NonePair(key, value) => Some((key, value)),
+                _ => None,
+            })
+            .map(|(key, value)| {
+                let push = if method == Method::Count {
+                    quote!(count += 1;)
+                } else if method == Method::Cow {
+                    quote! {
+                        query_params.push((
+                            ::std::borrow::Cow::Borrowed(#key),
+                            ::std::borrow::Cow::Borrowed(#value),
+                        ));
+                    }
+                } else {
+                    let pair = pair_expression(quote!(#key), quote!(#value.to_string()), encoding);
+                    quote!(query_params.push(#pair);)
+                };
+
+                quote! {
+                    else {
+                        #push
+                    }
+                }
+            });
+
+        let binding = if moved {
+            quote!(&mut self.#ident)
+        } else {
+            quote!(&self.#ident)
+        };
+
+        // `Result` fields are handled like `Option` fields, with `Err` in place of `None`
+        let (present, absent) = if is_result(&field.field.ty) {
+            (quote!(Ok), quote!(Err(_)))
+        } else {
+            (quote!(Some), quote!(None))
+        };
+
+        assignment = if field.attributes.contains(&FieldAttributes::Default) {
+            let ty = value_type(&field.field.ty);
+            let (declaration, default) = if moved {
+                (quote!(let mut default;), quote!(&mut default))
+            } else {
+                (quote!(let default;), quote!(&default))
+            };
+
+            quote! {
+                {
+                    #declaration
+                    let val = match #binding {
+                        #present(val) => val,
+                        #absent => {
+                            default = <#ty as ::std::default::Default>::default();
+                            #default
+                        }
+                    };
+                    #assignment
+                }
+            }
+        } else if field.attributes.contains(&FieldAttributes::Unwrap) {
+            let message = format!(
+                "query field `{}` was None, but is marked #[query(unwrap)]",
+                ident.unraw()
+            );
+
+            quote! {
+                {
+                    let val = match #binding {
+                        Some(val) => val,
+                        None => ::std::panic!(#message),
+                    };
+                    #assignment
+                }
+            }
+        } else {
+            quote! {
+                if let #present(val) = #binding {
+                    #assignment
+                } #none_assignment
+            }
+        };
+    }
+
+    for predicate in predicate_paths(field, |attr| match attr {
+        FieldAttributes::SkipFieldIf(path) => Some(path),
+        _ => None,
+    }) {
+        assignment = quote! {
+            if !(#predicate)(&self.#ident) {
+                #assignment
+            }
+        };
+    }
+
+    if method == Method::Owned {
+        if let Some((flag, _)) = exclude_flag(field) {
+            assignment = quote! {
+                if !#flag {
+                    #assignment
+                }
+            };
+        }
+
+        return assignment;
+    }
+
+    for condition in exclude_conditions(field, quote!(self)) {
+        assignment = quote! {
+            if !(#condition) {
+                #assignment
+            }
+        };
+    }
+
+    assignment
+}
+
+/// Produces the conditions excluding the field, from its `exclude_if` and `when` predicates called
+/// with `receiver`.
+fn exclude_conditions(field: &FieldDescription, receiver: TokenStream2) -> Vec<TokenStream2> {
+    let excluded = predicate_paths(field, |attr| match attr {
+        FieldAttributes::ExcludeIf(path) => Some(path),
+        _ => None,
+    })
+    .into_iter()
+    .map(|predicate| quote!((#predicate)(#receiver)));
+
+    let not_included = predicate_paths(field, |attr| match attr {
+        FieldAttributes::When(path) => Some(path),
+        _ => None,
+    })
+    .into_iter()
+    .map(|predicate| quote!(!(#predicate)(#receiver)));
+
+    excluded.chain(not_included).collect()
+}
+
+/// The name of a local flag holding whether the field is excluded, and the statement declaring
+/// it, for fields with `exclude_if` or `when` predicates.
+fn exclude_flag(field: &FieldDescription) -> Option<(Ident, TokenStream2)> {
+    let conditions = exclude_conditions(field, quote!(&self));
+
+    if conditions.is_empty() {
+        return None;
+    }
+
+    let flag = format_ident!("__exclude_{}", field.ident);
+    let declaration = quote! {
+        let #flag = #(#conditions)||*;
+    };
+
+    Some((flag, declaration))
+}
+
+/// Whether the field's value is a `String` emitted as-is, so `into_query_params` can move it into
+/// the output instead of cloning it.
+fn moves_value(field: &FieldDescription) -> bool {
+    emits_string(field) && aliases(field).is_empty()
+}
+
+/// Whether the field's value is a `String` emitted as-is, so `to_query_params_cow` can borrow it
+/// from the struct. Defaults only live as long as the call, so they're always formatted.
+fn borrows_value(field: &FieldDescription) -> bool {
+    emits_string(field) && !field.attributes.contains(&FieldAttributes::Default)
+}
+
+/// Whether the field's value is a `String` emitted without any formatting.
+fn emits_string(field: &FieldDescription) -> bool {
+    if !type_is(value_type(&field.field.ty), "String")
+        || !field.transforms.is_empty()
+        || has_dynamic_keys(field)
+    {
+        return false;
+    }
+
+    !field.attributes.iter().any(|attr| match attr {
+        FieldAttributes::Format(_) | FieldAttributes::WithTry(_) => true,
+        #[cfg(feature = "serde_json")]
+        FieldAttributes::Json => true,
+        #[cfg(feature = "base64")]
+        FieldAttributes::Base64 => true,
+        _ => false,
+    })
+}
+
+/// The additional keys from the field's `alias` attributes, in the order they're declared.
+fn aliases<'a>(field: &'a FieldDescription) -> Vec<&'a String> {
+    field
+        .attributes
+        .iter()
+        .filter_map(|attr| match attr {
+            FieldAttributes::Alias(alias) => Some(alias),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether the field's keys are only known at runtime, so it can't be checked or listed upfront.
+fn has_dynamic_keys(field: &FieldDescription) -> bool {
+    #[cfg(feature = "serde_json")]
+    if field.attributes.contains(&FieldAttributes::ValueObject) {
+        return true;
+    }
+
+    field.attributes.contains(&FieldAttributes::Nested)
+        || field.attributes.contains(&FieldAttributes::Indexed)
+        || field.attributes.contains(&FieldAttributes::Flatten)
+}
+
+/// Produces the statements pushing the pair(s) for `reference`, a reference to the field's value.
+fn field_push(
+    field: &FieldDescription,
+    reference: TokenStream2,
+    encoding: Encoding,
+    method: Method,
+) -> TokenStream2 {
+    let reference = deref_pointer(field, reference);
+
+    if method == Method::Cow {
+        return cow_push(field, reference);
+    }
+
+    let fallible = matches!(method, Method::Try { .. });
+    let redacted =
+        method == Method::Redacted && field.attributes.contains(&FieldAttributes::Sensitive);
+
+    #[cfg(feature = "serde_json")]
+    if field.attributes.contains(&FieldAttributes::ValueObject) {
+        let value = if redacted {
+            quote!(::std::string::String::from(REDACTED))
+        } else {
+            quote!(value)
+        };
+        let pair = pair_expression(quote!(&key), value, encoding);
+        return quote! {
+            for (key, value) in _to_query_params::__private::value_object_pairs(#reference) {
+                query_params.push(#pair);
+            }
+        };
+    }
+
+    if field.attributes.contains(&FieldAttributes::Flatten) {
+        let value = if redacted {
+            quote!(::std::string::String::from(REDACTED))
+        } else {
+            quote!(::std::string::ToString::to_string(value))
+        };
+        let pair = pair_expression(quote!(key), value, encoding);
+        return quote! {
+            for (key, value) in ::std::iter::IntoIterator::into_iter(#reference) {
+                query_params.push(#pair);
+            }
+        };
+    }
+
+    let name = &field.field_name;
+
+    let indexed = field.attributes.contains(&FieldAttributes::Indexed);
+
+    let nested = field.attributes.contains(&FieldAttributes::Nested) || indexed;
+
+    // the child encodes its own pairs, keeping its encoding attributes, so only the field's name
+    // and the brackets around the child's keys are encoded here
+    if nested
+        && (encoding.key || encoding.value)
+        && matches!(method, Method::Encoded | Method::Write { .. })
+    {
+        let (prefix, key) = match (encoding.key, indexed) {
+            (true, true) => (
+                encode_expression(encoding.scheme, quote!(#name)),
+                quote!(_to_query_params::__private::encoded_indexed_key(
+                    &prefix, index, &key
+                )),
+            ),
+            (true, false) => (
+                encode_expression(encoding.scheme, quote!(#name)),
+                quote!(::std::format!("{}%5B{}%5D", prefix, key)),
+            ),
+            (false, true) => (
+                quote!(#name),
+                quote!(_to_query_params::__private::indexed_key(
+                    prefix, index, &key
+                )),
+            ),
+            (false, false) => (quote!(#name), quote!(::std::format!("{}[{}]", prefix, key))),
+        };
+
+        let push = if encoding.streamed {
+            quote! {
+                query_params.push((
+                    _to_query_params::__private::QueryPart::Raw(&key),
+                    _to_query_params::__private::QueryPart::Raw(&value),
+                ));
+            }
+        } else {
+            quote!(query_params.push((key, value));)
+        };
+
+        let pairs = |child: TokenStream2| {
+            quote! {
+                for (key, value) in _to_query_params::ToQueryParams::to_encoded_params(#child) {
+                    let key = #key;
+                    #push
+                }
+            }
+        };
+
+        let pairs = if indexed {
+            let pairs = pairs(quote!(child));
+            quote! {
+                for (index, child) in ::std::iter::IntoIterator::into_iter(#reference).enumerate() {
+                    #pairs
+                }
+            }
+        } else {
+            pairs(reference)
+        };
+
+        return quote! {
+            {
+                let prefix = #prefix;
+                #pairs
+            }
+        };
+    }
+
+    if nested {
+        let value = if redacted {
+            quote!(::std::string::String::from(REDACTED))
+        } else {
+            quote!(value)
+        };
+        let pair = pair_expression(quote!(&key), value, encoding);
+        let pairs = |child: TokenStream2| match method {
+            Method::Try { .. } => {
+                quote!(_to_query_params::TryToQueryParams::try_to_query_params(#child)?)
+            }
+            Method::Redacted => {
+                quote!(_to_query_params::ToQueryParams::to_redacted_params(#child))
+            }
+            _ => quote!(_to_query_params::ToQueryParams::to_query_params(#child)),
+        };
+
+        if indexed {
+            let pairs = pairs(quote!(child));
+
+            return quote! {
+                for (index, child) in ::std::iter::IntoIterator::into_iter(#reference).enumerate() {
+                    for (key, value) in #pairs {
+                        let key = _to_query_params::__private::indexed_key(#name, index, &key);
+                        query_params.push(#pair);
+                    }
+                }
+            };
+        }
+
+        let pairs = pairs(reference);
+
+        return quote! {
+            for (key, value) in #pairs {
+                let key = ::std::format!("{}[{}]", #name, key);
+                query_params.push(#pair);
+            }
+        };
+    }
+
+    let value = if redacted {
+        quote!(::std::string::String::from(REDACTED))
+    } else {
+        value_expression(field, reference, fallible)
+    };
+
+    let aliases = aliases(field);
+
+    if aliases.is_empty() {
+        let pair = pair_expression(quote!(#name), value, encoding);
+
+        return quote! {
+            query_params.push(#pair);
+        };
+    }
+
+    // the value is formatted once, and cloned for the field's key and each of its aliases
+    let pairs = std::iter::once(name).chain(aliases).map(|key| {
+        pair_expression(
+            quote!(#key),
+            quote!(::std::clone::Clone::clone(&value)),
+            encoding,
+        )
+    });
+
+    quote! {
+        {
+            let value: ::std::string::String = #value;
+            #(query_params.push(#pairs);)*
+        }
+    }
+}
+
+/// Produces the statements pushing the pair(s) for `reference` as `Cow`s for `Method::Cow`,
+/// borrowing the key literals and the values that don't need formatting.
+fn cow_push(field: &FieldDescription, reference: TokenStream2) -> TokenStream2 {
+    let cow = quote!(::std::borrow::Cow);
+
+    #[cfg(feature = "serde_json")]
+    if field.attributes.contains(&FieldAttributes::ValueObject) {
+        return quote! {
+            for (key, value) in _to_query_params::__private::value_object_pairs(#reference) {
+                query_params.push((#cow::Owned(key), #cow::Owned(value)));
+            }
+        };
+    }
+
+    if field.attributes.contains(&FieldAttributes::Flatten) {
+        return quote! {
+            for (key, value) in ::std::iter::IntoIterator::into_iter(#reference) {
+                query_params.push((
+                    #cow::Owned(::std::string::ToString::to_string(key)),
+                    #cow::Owned(::std::string::ToString::to_string(value)),
+                ));
+            }
+        };
+    }
+
+    let name = &field.field_name;
+
+    let indexed = field.attributes.contains(&FieldAttributes::Indexed);
+
+    if field.attributes.contains(&FieldAttributes::Nested) || indexed {
+        // a default child only lives as long as the call, so its values can't be borrowed
+        let value = if field.attributes.contains(&FieldAttributes::Default) {
+            quote!(#cow::Owned(value.into_owned()))
+        } else {
+            quote!(value)
+        };
+
+        if indexed {
+            return quote! {
+                for (index, child) in ::std::iter::IntoIterator::into_iter(#reference).enumerate() {
+                    for (key, value) in _to_query_params::ToQueryParams::to_query_params_cow(child) {
+                        let key = _to_query_params::__private::indexed_key(#name, index, &key);
+                        query_params.push((#cow::Owned(key), #value));
+                    }
+                }
+            };
+        }
+
+        return quote! {
+            for (key, value) in _to_query_params::ToQueryParams::to_query_params_cow(#reference) {
+                query_params.push((#cow::Owned(::std::format!("{}[{}]", #name, key)), #value));
+            }
+        };
+    }
+
+    let value = if borrows_value(field) {
+        quote!(#cow::Borrowed(::std::string::String::as_str(#reference)))
+    } else {
+        let value = value_expression(field, reference, false);
+        quote!(#cow::Owned(#value))
+    };
+
+    let aliases = aliases(field);
+
+    if aliases.is_empty() {
+        return quote! {
+            query_params.push((#cow::Borrowed(#name), #value));
+        };
+    }
+
+    let keys = std::iter::once(name).chain(aliases);
+
+    quote! {
+        {
+            let value = #value;
+            #(query_params.push((#cow::Borrowed(#keys), ::std::clone::Clone::clone(&value)));)*
+        }
+    }
+}
+
+/// Dereferences `reference` to the pointee for `Box`, `Rc` and `Arc` fields, so it's formatted and
+/// validated as if it were the field's type.
+fn deref_pointer(field: &FieldDescription, reference: TokenStream2) -> TokenStream2 {
+    if pointee_type(value_type(&field.field.ty)).is_some() {
+        quote!(&**(#reference))
+    } else {
+        reference
+    }
+}
+
+/// Produces the statements checking the field's validation attributes against `reference`, a
+/// reference to the field's value. A failed check returns a `QueryParamsError::Invalid` if
+/// `fallible` is set, and panics with it otherwise.
+fn validation_checks(
+    field: &FieldDescription,
+    reference: TokenStream2,
+    fallible: bool,
+) -> TokenStream2 {
+    let reference = deref_pointer(field, reference);
+    let mut checks = Vec::new();
+
+    if field.attributes.contains(&FieldAttributes::NonEmpty) {
+        checks.push((
+            quote!((#reference).is_empty()),
+            "must not be empty".to_string(),
+        ));
+    }
+
+    for attribute in field.attributes.iter() {
+        if let FieldAttributes::Min(min) = attribute {
+            let bound: TokenStream2 = min.parse().expect("numbers are validated during parsing");
+            checks.push((
+                quote!(::std::cmp::PartialOrd::lt(#reference, &(#bound))),
+                format!("must be at least {min}"),
+            ));
+        }
+    }
+
+    for attribute in field.attributes.iter() {
+        if let FieldAttributes::Max(max) = attribute {
+            let bound: TokenStream2 = max.parse().expect("numbers are validated during parsing");
+            checks.push((
+                quote!(::std::cmp::PartialOrd::gt(#reference, &(#bound))),
+                format!("must be at most {max}"),
+            ));
+        }
+    }
+
+    #[cfg(feature = "regex")]
+    for attribute in field.attributes.iter() {
+        if let FieldAttributes::Pattern(pattern) = attribute {
+            checks.push((
+                quote! {
+                    !{
+                        static PATTERN: _to_query_params::__private::Pattern =
+                            _to_query_params::__private::Pattern::new();
+                        _to_query_params::__private::matches_pattern(
+                            &PATTERN,
+                            #pattern,
+                            ::std::convert::AsRef::<str>::as_ref(#reference),
+                        )
+                    }
+                },
+                format!("must match the pattern `{pattern}`"),
+            ));
+        }
+    }
+
+    let name = field.ident.unraw().to_string();
+
+    checks
+        .into_iter()
+        .map(|(failed, reason)| {
+            let error = quote! {
+                _to_query_params::QueryParamsError::Invalid {
+                    field: #name,
+                    reason: ::std::string::String::from(#reason),
+                }
+            };
+
+            let fail = if fallible {
+                quote!(return ::std::result::Result::Err(#error);)
+            } else {
+                quote!(::std::panic!("{}", #error);)
+            };
+
+            quote! {
+                if #failed {
+                    #fail
+                }
+            }
+        })
+        .collect()
+}
+
+/// Produces the statements adding the number of pairs the field emits to `count`, without
+/// formatting its value.
+fn field_count(field: &FieldDescription, reference: TokenStream2) -> TokenStream2 {
+    let reference = deref_pointer(field, reference);
+
+    #[cfg(feature = "serde_json")]
+    if field.attributes.contains(&FieldAttributes::ValueObject) {
+        return quote! {
+            count += _to_query_params::__private::value_object_len(#reference);
+        };
+    }
+
+    if field.attributes.contains(&FieldAttributes::Nested) {
+        return quote! {
+            count += _to_query_params::ToQueryParams::params_len(#reference);
+        };
+    }
+
+    if field.attributes.contains(&FieldAttributes::Indexed) {
+        return quote! {
+            for child in ::std::iter::IntoIterator::into_iter(#reference) {
+                count += _to_query_params::ToQueryParams::params_len(child);
+            }
+        };
+    }
+
+    if field.attributes.contains(&FieldAttributes::Flatten) {
+        return quote! {
+            count += ::std::iter::Iterator::count(::std::iter::IntoIterator::into_iter(#reference));
+        };
+    }
+
+    let pairs = 1 + aliases(field).len();
+
+    quote! {
+        count += #pairs;
+    }
+}
+
+/// Whether the key and value of a pair are url-encoded, and how.
+#[derive(Debug, Clone, Copy)]
+struct Encoding {
+    key: bool,
+    value: bool,
+    scheme: Scheme,
+    /// Whether the pair is pushed onto a `QueryWriter` or `MeasuredPairs` as `QueryPart`s instead
+    /// of `String`s.
+    streamed: bool,
+}
+
+/// How the encoded parts of a pair are url-encoded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scheme {
+    /// `urlencoding::encode`, leaving only RFC 3986 unreserved characters as-is.
+    Standard,
+    /// `EncodeSet::QueryLenient`, from `#[query(encode = "component")]`.
+    Component,
+    /// `EncodeSet::FormUrlEncoded`, from `#[query(encode = "form")]`.
+    Form,
+}
+
+impl Encoding {
+    /// The encoding used for a field in the generated `method`.
+    fn for_field(field: &FieldDescription, method: Method) -> Encoding {
+        let encoded = matches!(
+            method,
+            Method::Encoded
+                | Method::Write { encoded: true }
+                | Method::Try {
+                    measured: true,
+                    encoded: true
+                }
+        );
+
+        let mode = field.attributes.iter().find_map(|attr| match attr {
+            FieldAttributes::Raw => Some("none"),
+            FieldAttributes::Encode(mode) => Some(mode.as_str()),
+            _ => None,
+        });
+
+        let (key, value) = match mode {
+            Some("none") => (false, false),
+            Some("key_only") => (true, false),
+            Some("value_only") => (false, true),
+            _ => (true, true),
+        };
+
+        let scheme = match mode {
+            Some("component") => Scheme::Component,
+            Some("form") => Scheme::Form,
+            _ => Scheme::Standard,
+        };
+
+        Encoding {
+            key: encoded && key,
+            value: encoded && value,
+            scheme,
+            streamed: matches!(
+                method,
+                Method::Write { .. } | Method::Try { measured: true, .. }
+            ),
+        }
+    }
+}
+
+/// Produces a `(String, String)` from `key`, a `&str`, and `value`, a `String`, url-encoding
+/// either according to `encoding`.
+fn pair_expression(key: TokenStream2, value: TokenStream2, encoding: Encoding) -> TokenStream2 {
+    if encoding.streamed {
+        return streamed_pair_expression(key, value, encoding);
+    }
+
+    let encode = |part: TokenStream2| encode_expression(encoding.scheme, part);
+
+    let key = if encoding.key {
+        encode(key)
+    } else {
+        quote!(::std::string::ToString::to_string(#key))
+    };
+
+    let value = if encoding.value {
+        encode(quote!(&#value))
+    } else {
+        value
+    };
+
+    quote! {
+        (
+            #key,
+            #value
+        )
+    }
+}
+
+/// Produces an expression url-encoding `part`, a `&str`, into a `String` according to `scheme`.
+fn encode_expression(scheme: Scheme, part: TokenStream2) -> TokenStream2 {
+    match scheme {
+        Scheme::Standard => quote!(_to_query_params::urlencoding::encode(#part).into_owned()),
+        Scheme::Component => quote!(_to_query_params::EncodeSet::QueryLenient.encode(#part)),
+        Scheme::Form => quote!(_to_query_params::EncodeSet::FormUrlEncoded.encode(#part)),
+    }
+}
+
+/// Produces a `(QueryPart, QueryPart)` borrowing `key` and `value`, to be url-encoded by the
+/// `QueryWriter` according to `encoding` as they're written.
+fn streamed_pair_expression(
+    key: TokenStream2,
+    value: TokenStream2,
+    encoding: Encoding,
+) -> TokenStream2 {
+    let part = |part: TokenStream2, encoded: bool| {
+        let set = match encoding.scheme {
+            Scheme::Standard => quote!(Rfc3986Strict),
+            Scheme::Component => quote!(QueryLenient),
+            Scheme::Form => quote!(FormUrlEncoded),
+        };
+
+        if encoded {
+            quote!(_to_query_params::__private::QueryPart::Encoded(
+                &#part,
+                _to_query_params::EncodeSet::#set,
+            ))
+        } else {
+            quote!(_to_query_params::__private::QueryPart::Raw(&#part))
+        }
+    };
+
+    let key = part(key, encoding.key);
+    let value = part(value, encoding.value);
+
+    quote! {
+        (
+            #key,
+            #value
+        )
+    }
+}
+
+/// Parses the paths to predicate functions held by the attributes selected by `select`, in the
+/// order they're declared.
+fn predicate_paths<F>(field: &FieldDescription, select: F) -> Vec<ExprPath>
+where
+    F: Fn(&FieldAttributes) -> Option<&String>,
+{
+    field
+        .attributes
+        .iter()
+        .filter_map(select)
+        .map(|path| syn::parse_str(path).expect("predicate paths are validated during parsing"))
+        .collect()
+}
+
+/// Produces an expression converting `value`, a reference to the field's value (the inner `T` for
+/// `Option<T>` fields), into the `String` used as the query parameter's value, after applying any
+/// transforms in order.
+fn value_expression(field: &FieldDescription, value: TokenStream2, fallible: bool) -> TokenStream2 {
+    field.transforms.iter().fold(
+        format_value(field, value, fallible),
+        |value, transform| quote!((#transform)(#value)),
+    )
+}
+
+/// Produces an expression formatting `value` as a `String`, special-casing types that don't
+/// implement `Display` and any formatting attributes.
+///
+/// Formatting that can fail returns a `QueryParamsError` with `?` if `fallible` is set, and panics
+/// with it otherwise.
+fn format_value(field: &FieldDescription, value: TokenStream2, fallible: bool) -> TokenStream2 {
+    let ty = formatted_type(&field.field.ty);
+
+    let field_error = |result: TokenStream2| {
+        let name = field.ident.unraw().to_string();
+        if fallible {
+            quote! {
+                #result.map_err(|error| _to_query_params::QueryParamsError::new(#name, error))?
+            }
+        } else {
+            quote! {
+                #result.unwrap_or_else(|error| {
+                    ::std::panic!("{}", _to_query_params::QueryParamsError::new(#name, error))
+                })
+            }
+        }
+    };
+
+    for attribute in field.attributes.iter() {
+        if let FieldAttributes::WithTry(function) = attribute {
+            let function: ExprPath =
+                syn::parse_str(function).expect("function paths are validated during parsing");
+            return field_error(quote!((#function)(#value)));
+        }
+    }
+
+    if type_is(ty, "SystemTime") {
+        let millis = field
+            .attributes
+            .contains(&FieldAttributes::Epoch("millis".to_string()));
+        let unit = if millis {
+            quote!(as_millis)
+        } else {
+            quote!(as_secs)
+        };
+
+        // times before the epoch saturate to 0
+        return quote! {
+            ::std::time::SystemTime::duration_since(#value, ::std::time::UNIX_EPOCH)
+                .map(|duration| duration.#unit())
+                .unwrap_or(0)
+                .to_string()
+        };
+    }
+
+    if is_std_duration(ty) {
+        let millis = field
+            .attributes
+            .contains(&FieldAttributes::Duration("millis".to_string()));
+        let unit = if millis {
+            quote!(as_millis)
+        } else {
+            quote!(as_secs)
+        };
+
+        return quote!(::std::time::Duration::#unit(#value).to_string());
+    }
+
+    if type_is(ty, "PathBuf") || matches!(ty, Type::Reference(r) if type_is(&r.elem, "Path")) {
+        return quote!((#value).to_string_lossy().into_owned());
+    }
+
+    for attribute in field.attributes.iter() {
+        if let FieldAttributes::BoolFormat(truthy, falsy) = attribute {
+            return quote! {
+                ::std::string::String::from(if *#value { #truthy } else { #falsy })
+            };
+        }
+    }
+
+    if field.attributes.contains(&FieldAttributes::BracketIpv6) {
+        if type_is(ty, "IpAddr") {
+            return quote! {
+                match #value {
+                    ::std::net::IpAddr::V6(addr) => ::std::format!("[{}]", addr),
+                    addr => ::std::string::ToString::to_string(addr),
+                }
+            };
+        }
+
+        return quote!(::std::format!("[{}]", #value));
+    }
+
+    for attribute in field.attributes.iter() {
+        if let FieldAttributes::Format(format) = attribute {
+            return quote!(::std::format!(#format, #value));
+        }
+    }
+
+    #[cfg(feature = "serde_json")]
+    if field.attributes.contains(&FieldAttributes::Json) {
+        return field_error(quote!(_to_query_params::serde_json::to_string(#value)));
+    }
+
+    #[cfg(feature = "base64")]
+    if field.attributes.contains(&FieldAttributes::Base64) {
+        return quote! {
+            _to_query_params::base64::Engine::encode(
+                &_to_query_params::base64::engine::general_purpose::URL_SAFE_NO_PAD,
+                #value
+            )
+        };
+    }
+
+    #[cfg(feature = "chrono")]
+    {
+        for attribute in field.attributes.iter() {
+            if let FieldAttributes::DatetimeFormat(format) = attribute {
+                return quote!((#value).format(#format).to_string());
+            }
+        }
+
+        if is_crate_type(ty, "chrono", "DateTime") {
+            return quote!((#value).to_rfc3339());
+        }
+    }
+
+    #[cfg(feature = "time")]
+    {
+        for attribute in field.attributes.iter() {
+            if let FieldAttributes::TimeFormat(format) = attribute {
+                return field_error(quote! {
+                    (#value).format({
+                        static FORMAT: _to_query_params::__private::TimeFormat =
+                            _to_query_params::__private::TimeFormat::new();
+                        _to_query_params::__private::time_format(&FORMAT, #format)
+                    })
+                });
+            }
+        }
+
+        if is_crate_type(ty, "time", "OffsetDateTime") {
+            return field_error(quote! {
+                (#value).format(&_to_query_params::time::format_description::well_known::Rfc3339)
+            });
+        }
+    }
+
+    #[cfg(feature = "uuid")]
+    for attribute in field.attributes.iter() {
+        if let FieldAttributes::Uuid(format) = attribute {
+            let formatter = Ident::new(&format!("as_{format}"), proc_macro2::Span::call_site());
+            return quote!(::std::string::ToString::to_string(&(#value).#formatter()));
+        }
+    }
+
+    quote!(::std::string::ToString::to_string(#value))
+}
+
+/// Returns `T` for a `Box<T>`, `Rc<T>` or `Arc<T>`, or `None` for anything else.
+fn pointee_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+
+    let segment = type_path.path.segments.last()?;
+    if type_path.qself.is_some()
+        || !["Box", "Rc", "Arc"].contains(&segment.ident.to_string().as_str())
+    {
+        return None;
+    }
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first() {
+            Some(GenericArgument::Type(inner)) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the type that's formatted for a field, which is its `value_type` with any `Box`, `Rc`
+/// or `Arc` removed.
+fn formatted_type(ty: &Type) -> &Type {
+    let ty = value_type(ty);
+    pointee_type(ty).unwrap_or(ty)
+}
+
+/// Returns `T` for an `Option<T>` or `Result<T, E>`, or the type itself for anything else.
+fn value_type(ty: &Type) -> &Type {
+    if let Type::Path(type_path) = ty {
+        if type_path.qself.is_none()
+            && (path_is_option(&type_path.path) || path_is_result(&type_path.path))
+        {
+            let segment = type_path.path.segments.last().unwrap();
+            if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                if let Some(GenericArgument::Type(inner)) = args.args.first() {
+                    return inner;
+                }
+            }
+        }
+    }
+
+    ty
+}
+
+/// Checks if the last segment of a type's path is `name`, e.g. `std::time::SystemTime` is "SystemTime".
+fn type_is(ty: &Type, name: &str) -> bool {
+    match ty {
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == name),
+        _ => false,
+    }
+}
+
+/// Checks if a type is `krate`'s type `name`, either imported or as `krate::name`, but not another
+/// crate's type of the same name, which is left to its own `Display`.
+#[cfg(any(feature = "chrono", feature = "time"))]
+fn is_crate_type(ty: &Type, krate: &str, name: &str) -> bool {
+    match ty {
+        Type::Path(type_path) => {
+            let segments = &type_path.path.segments;
+
+            match segments.len() {
+                1 => segments[0].ident == name,
+                2 => segments[0].ident == krate && segments[1].ident == name,
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Checks if a type is `std::time::Duration`, either imported or by its full path, but not another
+/// crate's `Duration`, like `chrono::Duration`, which implements `Display`.
+fn is_std_duration(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) => {
+            let segments: Vec<String> = type_path
+                .path
+                .segments
+                .iter()
+                .map(|segment| segment.ident.to_string())
+                .collect();
+            let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+            matches!(
+                segments[..],
+                ["Duration"] | ["time", "Duration"] | ["std" | "core", "time", "Duration"]
+            )
+        }
+        _ => false,
+    }
+}
+
+/// Checks that no two fields emit the same key, which is usually a copy-paste mistake in a rename.
+fn validate_unique_keys(field_descriptions: &[FieldDescription]) -> syn::Result<()> {
+    let mut seen: Vec<(&String, &FieldDescription)> = Vec::new();
+
+    for field in field_descriptions {
+        if has_dynamic_keys(field) {
+            continue;
+        }
+
+        for key in std::iter::once(&field.field_name).chain(aliases(field)) {
+            if let Some((_, other)) = seen.iter().find(|(seen_key, _)| *seen_key == key) {
+                return Err(syn::Error::new_spanned(
+                    &field.ident,
+                    format!(
+                        "duplicate query key `{}`, also used by field `{}`; add #[query(allow_duplicate_keys)] to the struct if this is intentional",
+                        key, other.ident
+                    ),
+                ));
+            }
+
+            seen.push((key, field));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_optional_field(field_desc: &FieldDescription) -> syn::Result<()> {
+    if let Type::Path(type_path) = &field_desc.field.ty {
+        if !(type_path.qself.is_none()
+            && (path_is_option(&type_path.path) || path_is_result(&type_path.path)))
+        {
+            return Err(syn::Error::new_spanned(
+                &field_desc.field.ty,
+                "Non-optional types must be marked with #[query(required)] attribute",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that a required field isn't an `Option`, which doesn't implement `Display`.
+fn validate_required_field(field_desc: &FieldDescription) -> syn::Result<()> {
+    if is_option(&field_desc.field.ty) {
+        return Err(syn::Error::new_spanned(
+            &field_desc.field.ty,
+            "#[query(required)] can't be used on `Option` fields, remove `required` to emit the value \
+             only when it's `Some`, or use #[query(unwrap)] to always emit it",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks if a type is `Option<T>`, in any of the forms accepted by `path_is_option`.
+fn is_option(ty: &Type) -> bool {
+    matches!(ty, Type::Path(type_path) if type_path.qself.is_none() && path_is_option(&type_path.path))
+}
+
+/// Checks if a type is `Result<T, E>`, in any of the forms accepted by `path_is_result`.
+fn is_result(ty: &Type) -> bool {
+    matches!(ty, Type::Path(type_path) if type_path.qself.is_none() && path_is_result(&type_path.path))
+}
+
+/// Checks if a path is `Result`, or one of its fully-qualified forms like `std::result::Result`.
+fn path_is_result(path: &Path) -> bool {
+    let segments: Vec<String> = path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect();
+
+    match segments.as_slice() {
+        [result] => path.leading_colon.is_none() && result == "Result",
+        [krate, module, result] => {
+            (krate == "std" || krate == "core") && module == "result" && result == "Result"
+        }
+        _ => false,
+    }
+}
+
+/// Checks if a path is `Option`, or one of its fully-qualified forms like `std::option::Option`.
+fn path_is_option(path: &Path) -> bool {
+    let segments: Vec<String> = path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect();
+
+    match segments.as_slice() {
+        [option] => path.leading_colon.is_none() && option == "Option",
+        [krate, module, option] => {
+            (krate == "std" || krate == "core") && module == "option" && option == "Option"
+        }
+        _ => false,
+    }
 }
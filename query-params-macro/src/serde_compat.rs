@@ -0,0 +1,82 @@
+//! Support for reading `#[serde(rename = "...")]` and `#[serde(rename_all = "...")]` as fallback
+//! names for fields, enabled by the `serde-compat` feature.
+//!
+//! Explicit `#[query(rename = "...")]` attributes always take precedence over anything found here,
+//! and `#[query(rename_all = "...")]` takes precedence over `#[serde(rename_all = "...")]`.
+use crate::case::RenameRule;
+use crate::{FieldAttributes, FieldDescription};
+use syn::ext::IdentExt;
+use syn::meta::ParseNestedMeta;
+use syn::{Attribute, LitStr, Token};
+
+/// Renames every field that doesn't carry an explicit `#[query(rename = "...")]`, using its
+/// `#[serde(rename = "...")]` if present, or otherwise the container's `#[serde(rename_all = "...")]`.
+/// The latter is skipped if `has_query_rename_all` is set, since those fields were already renamed.
+pub(crate) fn apply_serde_names<'f>(
+    container_attrs: &[Attribute],
+    has_query_rename_all: bool,
+    mut field_descriptions: Vec<FieldDescription<'f>>,
+) -> Vec<FieldDescription<'f>> {
+    let rename_rule = container_attrs
+        .iter()
+        .find_map(parse_serde_rename_all)
+        .filter(|_| !has_query_rename_all);
+
+    for desc in field_descriptions.iter_mut() {
+        let explicit_rename = desc
+            .attributes
+            .iter()
+            .any(|attr| matches!(attr, FieldAttributes::Rename(_)));
+
+        if explicit_rename {
+            continue;
+        }
+
+        if let Some(rename) = desc.field.attrs.iter().find_map(parse_serde_rename) {
+            desc.field_name = rename;
+        } else if let Some(rule) = rename_rule {
+            desc.field_name = rule.apply_to_field(&desc.ident.unraw().to_string());
+        }
+    }
+
+    field_descriptions
+}
+
+fn parse_serde_rename(attr: &Attribute) -> Option<String> {
+    parse_serde_string_value(attr, "rename")
+}
+
+fn parse_serde_rename_all(attr: &Attribute) -> Option<RenameRule> {
+    parse_serde_string_value(attr, "rename_all").and_then(|rule| RenameRule::from_name(&rule))
+}
+
+/// Finds `key = "value"` within a `#[serde(...)]` attribute. Malformed serde attributes are left
+/// for serde's own derive to report, so any parsing errors are ignored here.
+fn parse_serde_string_value(attr: &Attribute, key: &str) -> Option<String> {
+    let mut value = None;
+
+    if attr.path().is_ident("serde") {
+        let _ = attr.parse_nested_meta(|m| {
+            if m.path.is_ident(key) && m.input.peek(Token![=]) {
+                let lit: LitStr = m.value()?.parse()?;
+                value = Some(lit.value());
+            } else {
+                skip_nested_meta(&m)?;
+            }
+
+            Ok(())
+        });
+    }
+
+    value
+}
+
+fn skip_nested_meta(m: &ParseNestedMeta) -> syn::Result<()> {
+    if m.input.peek(Token![=]) {
+        m.value()?.parse::<syn::Expr>()?;
+    } else if m.input.peek(syn::token::Paren) {
+        m.parse_nested_meta(|nested| skip_nested_meta(&nested))?;
+    }
+
+    Ok(())
+}
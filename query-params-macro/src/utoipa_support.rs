@@ -0,0 +1,108 @@
+//! Generation of `utoipa::IntoParams` impls for `#[query(utoipa)]`, enabled by the `utoipa`
+//! feature.
+//!
+//! Each key is described after renaming, with whether it's always emitted and a string schema,
+//! since every value is sent as a string on the wire.
+use crate::{aliases, exclude_conditions, has_dynamic_keys, FieldAttributes, FieldDescription};
+use quote::quote;
+use syn::__private::TokenStream2;
+use syn::DeriveInput;
+
+/// Implements `utoipa::IntoParams`, listing a query parameter for each key the struct can emit, in
+/// the order of `QUERY_KEYS`. Fields whose keys are only known at runtime can't be described, so
+/// they're left out.
+pub(crate) fn into_params_impl(
+    ast: &DeriveInput,
+    field_descriptions: &[FieldDescription],
+) -> TokenStream2 {
+    let ident = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let mut keys: Vec<(&String, bool)> = Vec::new();
+
+    for field in field_descriptions {
+        if has_dynamic_keys(field) {
+            continue;
+        }
+
+        let none_keys: Vec<&String> = field
+            .attributes
+            .iter()
+            .filter_map(|attr| match attr {
+                FieldAttributes::NonePair(key, _) => Some(key),
+                _ => None,
+            })
+            .collect();
+
+        let present_keys: Vec<&String> = std::iter::once(&field.field_name)
+            .chain(aliases(field))
+            .collect();
+
+        for key in present_keys.iter().chain(none_keys.iter()) {
+            if keys.iter().any(|(listed, _)| listed == key) {
+                continue;
+            }
+
+            let required = present_keys.contains(key)
+                && (always_present(field) || none_keys.contains(key))
+                && !is_conditional(field);
+
+            keys.push((key, required));
+        }
+    }
+
+    let parameters = keys.iter().map(|(key, required)| {
+        let required = if *required {
+            quote!(Required::True)
+        } else {
+            quote!(Required::False)
+        };
+
+        quote! {
+            ParameterBuilder::new()
+                .name(#key)
+                .parameter_in(parameter_in_provider().unwrap_or(ParameterIn::Query))
+                .required(#required)
+                .schema(::std::option::Option::Some(RefOr::T(Schema::Object(
+                    ObjectBuilder::new().schema_type(Type::String).build(),
+                ))))
+                .build()
+        }
+    });
+
+    quote! {
+        impl #impl_generics _to_query_params::utoipa::IntoParams for #ident #ty_generics #where_clause {
+            fn into_params(
+                parameter_in_provider: impl Fn() -> ::std::option::Option<
+                    _to_query_params::utoipa::openapi::path::ParameterIn,
+                >,
+            ) -> ::std::vec::Vec<_to_query_params::utoipa::openapi::path::Parameter> {
+                use _to_query_params::utoipa::openapi::path::{ParameterBuilder, ParameterIn};
+                use _to_query_params::utoipa::openapi::schema::{ObjectBuilder, Schema, Type};
+                use _to_query_params::utoipa::openapi::{RefOr, Required};
+
+                ::std::vec![#(#parameters),*]
+            }
+        }
+    }
+}
+
+/// Whether the field always has a value to emit, rather than only when it's `Some` or `Ok`.
+fn always_present(field: &FieldDescription) -> bool {
+    field.attributes.iter().any(|attr| {
+        matches!(
+            attr,
+            FieldAttributes::Required | FieldAttributes::Default | FieldAttributes::Unwrap
+        )
+    })
+}
+
+/// Whether a predicate can skip or exclude the field at runtime.
+fn is_conditional(field: &FieldDescription) -> bool {
+    field.attributes.iter().any(|attr| {
+        matches!(
+            attr,
+            FieldAttributes::SkipIf(_) | FieldAttributes::SkipFieldIf(_)
+        )
+    }) || !exclude_conditions(field, quote!(self)).is_empty()
+}
@@ -0,0 +1,219 @@
+//! Runtime helpers called by the code generated from `#[derive(QueryParams)]`. These are not part
+//! of the public API.
+use crate::EncodeSet;
+use std::fmt;
+
+/// Converts a `serde_json::Value` object into (key, value) pairs for `#[query(value_object)]`.
+///
+/// Strings are emitted without quotes, numbers and booleans use their usual representation, and
+/// nested arrays and objects are emitted as JSON strings. `null` entries are skipped.
+///
+/// # Panics
+/// Panics if `value` is not a JSON object.
+#[cfg(feature = "serde_json")]
+pub fn value_object_pairs(value: &serde_json::Value) -> Vec<(String, String)> {
+    use serde_json::Value;
+
+    value_object(value)
+        .iter()
+        .filter_map(|(key, value)| match value {
+            Value::Null => None,
+            Value::String(string) => Some((key.clone(), string.clone())),
+            other => Some((key.clone(), other.to_string())),
+        })
+        .collect()
+}
+
+/// The number of pairs `value_object_pairs` would create for `value`, counted without formatting
+/// or collecting them.
+///
+/// # Panics
+/// Panics if `value` is not a JSON object.
+#[cfg(feature = "serde_json")]
+pub fn value_object_len(value: &serde_json::Value) -> usize {
+    value_object(value)
+        .values()
+        .filter(|value| !value.is_null())
+        .count()
+}
+
+#[cfg(feature = "serde_json")]
+fn value_object(value: &serde_json::Value) -> &serde_json::Map<String, serde_json::Value> {
+    match value {
+        serde_json::Value::Object(object) => object,
+        other => panic!("#[query(value_object)] field must be a JSON object, but got: {other}"),
+    }
+}
+
+/// The lazily compiled regex of a `#[query(pattern = "...")]` attribute, held in a `static`.
+#[cfg(feature = "regex")]
+pub type Pattern = std::sync::OnceLock<regex::Regex>;
+
+/// Checks `value` against `pattern`, compiling it into `regex` on first use. The derive rejects
+/// invalid patterns at compile time, so compiling it here can't fail.
+#[cfg(feature = "regex")]
+pub fn matches_pattern(regex: &'static Pattern, pattern: &str, value: &str) -> bool {
+    regex
+        .get_or_init(|| {
+            regex::Regex::new(pattern).expect("#[query(pattern)] regexes are checked by the derive")
+        })
+        .is_match(value)
+}
+
+/// Creates the key of an item's pair for `#[query(indexed)]`, e.g. `items[0][name]`. Keys the item
+/// already bracketed, like `address[city]` from a `nested` field, keep their brackets as
+/// `items[0][address][city]`.
+pub fn indexed_key(name: &str, index: usize, key: &str) -> String {
+    bracketed_indexed_key(name, index, key, ["[", "]"])
+}
+
+/// Creates the key of an item's url-encoded pair for `#[query(indexed)]` from the item's own
+/// encoded key, e.g. `items%5B0%5D%5Bname%5D`, where `name` is already encoded.
+pub fn encoded_indexed_key(name: &str, index: usize, key: &str) -> String {
+    bracketed_indexed_key(name, index, key, ["%5B", "%5D"])
+}
+
+fn bracketed_indexed_key(name: &str, index: usize, key: &str, [open, close]: [&str; 2]) -> String {
+    match key.find(open) {
+        Some(bracket) => format!(
+            "{name}{open}{index}{close}{open}{}{close}{}",
+            &key[..bracket],
+            &key[bracket..]
+        ),
+        None => format!("{name}{open}{index}{close}{open}{key}{close}"),
+    }
+}
+
+/// Writes pairs into a `fmt::Write` as a query string, separated by `&`, for derived
+/// `write_query_string` implementations. The first error is kept and returned by `finish`, so the
+/// generated pushes don't need to handle it.
+pub struct QueryWriter<'w> {
+    w: &'w mut dyn fmt::Write,
+    empty: bool,
+    result: fmt::Result,
+}
+
+impl<'w> QueryWriter<'w> {
+    pub fn new(w: &'w mut dyn fmt::Write) -> Self {
+        QueryWriter {
+            w,
+            empty: true,
+            result: Ok(()),
+        }
+    }
+
+    /// Writes `key=value`, preceded by `&` unless it's the first pair.
+    pub fn push(&mut self, (key, value): (QueryPart<'_>, QueryPart<'_>)) {
+        if self.result.is_err() {
+            return;
+        }
+
+        self.result = (|| {
+            if !std::mem::take(&mut self.empty) {
+                self.w.write_char('&')?;
+            }
+            key.write(self.w)?;
+            self.w.write_char('=')?;
+            value.write(self.w)
+        })();
+    }
+
+    pub fn finish(self) -> fmt::Result {
+        self.result
+    }
+}
+
+/// A key or value pushed onto a [`QueryWriter`], either percent-encoded as it's written or written
+/// as-is.
+pub enum QueryPart<'a> {
+    Encoded(&'a str, EncodeSet),
+    Raw(&'a dyn fmt::Display),
+}
+
+impl QueryPart<'_> {
+    fn write(&self, w: &mut dyn fmt::Write) -> fmt::Result {
+        match self {
+            QueryPart::Encoded(part, set) => set.encode_into(part, w),
+            QueryPart::Raw(part) => write!(w, "{part}"),
+        }
+    }
+
+    /// The un-encoded part, and its length once written into a query string.
+    fn measure(&self) -> (String, usize) {
+        match self {
+            QueryPart::Encoded(part, set) => (part.to_string(), set.encoded_len(part)),
+            QueryPart::Raw(part) => {
+                let part = part.to_string();
+                let length = part.len();
+                (part, length)
+            }
+        }
+    }
+}
+
+/// Collects the un-encoded pairs for derived `try_to_query_params` implementations with a
+/// `#[query(max_length = N)]`, summing up the length of the query string they'd be written as.
+#[derive(Default)]
+pub struct MeasuredPairs {
+    pairs: Vec<(String, String)>,
+    length: usize,
+}
+
+impl MeasuredPairs {
+    pub fn new() -> Self {
+        MeasuredPairs::default()
+    }
+
+    /// Pushes the un-encoded pair, adding `key=value` and any `&` to the length.
+    pub fn push(&mut self, (key, value): (QueryPart<'_>, QueryPart<'_>)) {
+        let (key, key_length) = key.measure();
+        let (value, value_length) = value.measure();
+
+        if !self.pairs.is_empty() {
+            self.length += 1;
+        }
+        self.length += key_length + 1 + value_length;
+        self.pairs.push((key, value));
+    }
+
+    /// The un-encoded pairs, and the length of the query string.
+    pub fn finish(self) -> (Vec<(String, String)>, usize) {
+        (self.pairs, self.length)
+    }
+}
+
+/// Converts pairs into sorted `String` pairs for `assert_query_eq!`, so collections holding the
+/// same pairs in any order compare equal.
+pub fn sorted_pairs<I, K, V>(pairs: I) -> Vec<(String, String)>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: ToString,
+    V: ToString,
+{
+    let mut pairs: Vec<(String, String)> = pairs
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+    pairs.sort();
+    pairs
+}
+
+/// The lazily parsed format description of a `#[query(time_format = "...")]` attribute, held in a
+/// `static`.
+#[cfg(feature = "time")]
+pub type TimeFormat = std::sync::OnceLock<time::format_description::OwnedFormatItem>;
+
+/// Returns the format description `format`, parsing it into `parsed` on first use.
+///
+/// # Panics
+/// Panics if `format` is not a valid format description.
+#[cfg(feature = "time")]
+pub fn time_format(
+    parsed: &'static TimeFormat,
+    format: &str,
+) -> &'static time::format_description::OwnedFormatItem {
+    parsed.get_or_init(|| {
+        time::format_description::parse_owned::<2>(format)
+            .unwrap_or_else(|error| panic!("invalid #[query(time_format)] description: {error}"))
+    })
+}
@@ -0,0 +1,60 @@
+//! Integration with [`awc`](https://crates.io/crates/awc), the actix-web client.
+use crate::ToQueryParams;
+
+/// [`ClientRequestExt`] adds `query_params` to `awc::ClientRequest`, appending the url-encoded
+/// query string from [`ToQueryParams::to_query_string`] to the request's Uri.
+///
+/// Unlike awc's own `query`, which replaces the Uri's query, any query the request already has is
+/// kept. It's implemented with the `awc` feature enabled.
+pub trait ClientRequestExt: Sized {
+    /// Appends the query parameters produced by `params` to the request's Uri, keeping any query
+    /// it already has. An invalid result is reported when the request is sent, as with awc's `uri`.
+    fn query_params<T: ToQueryParams + ?Sized>(self, params: &T) -> Self;
+}
+
+impl ClientRequestExt for awc::ClientRequest {
+    fn query_params<T: ToQueryParams + ?Sized>(self, params: &T) -> Self {
+        let uri = params.to_url(&self.get_uri().to_string());
+        self.uri(uri)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::QueryParams;
+
+    #[derive(QueryParams)]
+    struct TestItem {
+        #[query(required)]
+        a: String,
+        b: Option<i32>,
+    }
+
+    #[test]
+    fn test_client_request_query_params() {
+        let test_item = TestItem {
+            a: "a&b=c ü".to_string(),
+            b: Some(1),
+        };
+
+        let request = awc::Client::default()
+            .get("https://api.example.com/items?page=2")
+            .query_params(&test_item);
+
+        assert_eq!(
+            request.get_uri().query(),
+            Some("page=2&a=a%26b%3Dc%20%C3%BC&b=1")
+        );
+        assert_eq!(request.get_uri().path(), "/items");
+
+        let request = awc::Client::default()
+            .get("/items")
+            .query_params(&TestItem {
+                a: "x".to_string(),
+                b: None,
+            });
+
+        assert_eq!(request.get_uri(), "/items?a=x");
+    }
+}
@@ -0,0 +1,243 @@
+//! A builder combining several sources of query parameters into one query.
+use crate::ToQueryParams;
+use std::borrow::Cow;
+use std::fmt;
+
+/// [`QueryParamsBuilder`] combines the pairs of several [`ToQueryParams`] values and ad-hoc pairs
+/// into one query, keeping them in the order they were added.
+///
+/// Each source's pairs are encoded by the source itself, so encoding attributes like
+/// `#[query(raw)]` are kept. Pairs added with `push` and `push_opt` are encoded the same way as
+/// derived fields without encoding attributes, so params only known at runtime can be mixed with
+/// derived ones. [`QueryParamsBuilder`] implements [`ToQueryParams`] too, so builders can be
+/// nested, or passed anywhere a derived struct can.
+///
+/// ```
+/// # use to_query_params::{QueryParams, QueryParamsBuilder, ToQueryParams};
+/// #[derive(QueryParams)]
+/// struct Page {
+///     #[query(required)]
+///     page: u32,
+/// }
+///
+/// let query = QueryParamsBuilder::new()
+///     .add(&Page { page: 2 })
+///     .push("q", "red shoes")
+///     .push_opt("limit", None::<u32>)
+///     .build_string();
+///
+/// assert_eq!(query, "page=2&q=red%20shoes");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryParamsBuilder {
+    params: Vec<(String, String)>,
+    encoded_params: Vec<(String, String)>,
+}
+
+impl QueryParamsBuilder {
+    /// Creates an empty builder.
+    pub fn new() -> Self {
+        QueryParamsBuilder::default()
+    }
+
+    /// Appends the pairs produced by `source`.
+    pub fn add<T: ToQueryParams + ?Sized>(&mut self, source: &T) -> &mut Self {
+        source.extend_params(&mut self.params);
+        self.encoded_params.extend(source.to_encoded_params());
+        self
+    }
+
+    /// Appends a single pair.
+    pub fn push(&mut self, key: &str, value: impl ToString) -> &mut Self {
+        let value = value.to_string();
+
+        self.encoded_params.push((
+            urlencoding::encode(key).into_owned(),
+            urlencoding::encode(&value).into_owned(),
+        ));
+        self.params.push((key.to_string(), value));
+        self
+    }
+
+    /// Appends a single pair if `value` is `Some`, and does nothing otherwise.
+    pub fn push_opt<V: ToString>(&mut self, key: &str, value: Option<V>) -> &mut Self {
+        if let Some(value) = value {
+            self.push(key, value);
+        }
+        self
+    }
+
+    /// Creates the un-encoded pairs added so far, as in `to_query_params`.
+    pub fn build(&self) -> Vec<(String, String)> {
+        self.params.clone()
+    }
+
+    /// Creates the url-encoded query string from the pairs added so far, as in `to_query_string`.
+    pub fn build_string(&self) -> String {
+        self.to_query_string()
+    }
+}
+
+impl ToQueryParams for QueryParamsBuilder {
+    fn to_query_params(&self) -> Vec<(String, String)> {
+        self.params.clone()
+    }
+
+    fn to_encoded_params(&self) -> Vec<(String, String)> {
+        self.encoded_params.clone()
+    }
+
+    fn extend_params(&self, out: &mut Vec<(String, String)>) {
+        out.extend_from_slice(&self.params);
+    }
+
+    fn to_query_params_cow(&self) -> Vec<(Cow<'static, str>, Cow<'_, str>)> {
+        self.params
+            .iter()
+            .map(|(key, value)| (Cow::Owned(key.clone()), Cow::Borrowed(value.as_str())))
+            .collect()
+    }
+
+    fn params_len(&self) -> usize {
+        self.params.len()
+    }
+
+    fn write_query_string(&self, w: &mut dyn fmt::Write) -> fmt::Result {
+        crate::write_pairs(w, &self.encoded_params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::QueryParams;
+
+    #[derive(QueryParams)]
+    struct TestPage {
+        #[query(required)]
+        page: u32,
+        limit: Option<u32>,
+    }
+
+    #[derive(QueryParams)]
+    struct TestFilter {
+        #[query(required, rename = "search term")]
+        term: String,
+        #[query(raw)]
+        path: Option<String>,
+    }
+
+    #[test]
+    fn test_query_params_builder() {
+        let mut builder = QueryParamsBuilder::new();
+        builder
+            .add(&TestPage {
+                page: 2,
+                limit: Some(50),
+            })
+            .push("api key", "a&b")
+            .add(&TestFilter {
+                term: "red shoes".to_string(),
+                path: Some("/a b".to_string()),
+            })
+            .push_opt("cursor", None::<String>)
+            .push_opt("debug", Some(true));
+
+        let expected = vec![
+            ("page".to_string(), "2".to_string()),
+            ("limit".to_string(), "50".to_string()),
+            ("api key".to_string(), "a&b".to_string()),
+            ("search term".to_string(), "red shoes".to_string()),
+            ("path".to_string(), "/a b".to_string()),
+            ("debug".to_string(), "true".to_string()),
+        ];
+
+        let expected_encoded = vec![
+            ("page".to_string(), "2".to_string()),
+            ("limit".to_string(), "50".to_string()),
+            ("api%20key".to_string(), "a%26b".to_string()),
+            ("search%20term".to_string(), "red%20shoes".to_string()),
+            ("path".to_string(), "/a b".to_string()),
+            ("debug".to_string(), "true".to_string()),
+        ];
+
+        assert_eq!(builder.build(), expected);
+        assert_eq!(builder.to_query_params(), expected);
+        assert_eq!(builder.to_encoded_params(), expected_encoded);
+        assert_eq!(builder.params_len(), 6);
+        assert_eq!(
+            builder.to_query_params_cow()[2],
+            (Cow::from("api key"), Cow::from("a&b"))
+        );
+        assert_eq!(
+            builder.build_string(),
+            "page=2&limit=50&api%20key=a%26b&search%20term=red%20shoes&path=/a b&debug=true"
+        );
+    }
+
+    #[test]
+    fn test_query_params_builder_matches_derive() {
+        let page = TestPage {
+            page: 2,
+            limit: None,
+        };
+        let filter = TestFilter {
+            term: "a&b c".to_string(),
+            path: None,
+        };
+
+        let mut builder = QueryParamsBuilder::new();
+        builder
+            .push("page", 2)
+            .push_opt("limit", None::<u32>)
+            .push("search term", "a&b c");
+
+        let mut derived = page.to_encoded_params();
+        derived.extend(filter.to_encoded_params());
+
+        assert_eq!(builder.to_encoded_params(), derived);
+        assert_eq!(builder.build_string(), "page=2&search%20term=a%26b%20c");
+
+        let mut derived = page.to_query_params();
+        derived.extend(filter.to_query_params());
+
+        assert_eq!(builder.build(), derived);
+    }
+
+    #[test]
+    fn test_query_params_builder_push() {
+        let params = QueryParamsBuilder::new()
+            .push("q", "red shoes")
+            .push_opt("limit", Some(20))
+            .push_opt("cursor", None::<&str>)
+            .push("debug", true)
+            .build();
+
+        let expected = vec![
+            ("q".to_string(), "red shoes".to_string()),
+            ("limit".to_string(), "20".to_string()),
+            ("debug".to_string(), "true".to_string()),
+        ];
+
+        assert_eq!(params, expected);
+        assert!(QueryParamsBuilder::new()
+            .push_opt("cursor", None::<&str>)
+            .build()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_query_params_builder_nested() {
+        let mut inner = QueryParamsBuilder::new();
+        inner.push("b", 2);
+
+        let outer = QueryParamsBuilder::new()
+            .push("a", 1)
+            .add(&inner)
+            .push("c", 3)
+            .build_string();
+
+        assert_eq!(outer, "a=1&b=2&c=3");
+        assert_eq!(QueryParamsBuilder::new().build_string(), "");
+    }
+}
@@ -0,0 +1,189 @@
+//! Percent-encoding schemes used by the provided methods on [`ToQueryParams`](crate::ToQueryParams).
+use std::fmt;
+
+/// [`EncodeSet`] selects which characters are percent-encoded by
+/// [`ToQueryParams::to_encoded_params_with`](crate::ToQueryParams::to_encoded_params_with).
+///
+/// In every mode, ASCII alphanumerics are never encoded, and non-ASCII characters are always
+/// encoded as their UTF-8 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodeSet {
+    /// Leaves every character that's allowed in a URL's query as-is (`-._~!$'()*,;:@/?`), encoding
+    /// only what would change the meaning of a key or value, like `&`, `=`, `+`, `#` and spaces.
+    QueryLenient,
+    /// Leaves only RFC 3986 unreserved characters (`-._~`) as-is. This matches `to_encoded_params`.
+    Rfc3986Strict,
+    /// Follows the `application/x-www-form-urlencoded` rules, leaving `*-._` as-is and encoding
+    /// spaces as `+`.
+    FormUrlEncoded,
+    /// Follows OAuth 1.0a's percent-encoding (RFC 5849, section 3.6), used when building signature
+    /// base strings. Only unreserved characters (`-._~`) are left as-is, so `+` is always `%2B`
+    /// and spaces are always `%20`.
+    OAuth1,
+}
+
+impl EncodeSet {
+    /// Percent-encodes `value` according to this set.
+    pub fn encode(&self, value: &str) -> String {
+        let mut encoded = String::with_capacity(value.len());
+        self.encode_into(value, &mut encoded)
+            .expect("writing to a String can't fail");
+        encoded
+    }
+
+    /// The length of `value` once percent-encoded according to this set, without encoding it.
+    pub(crate) fn encoded_len(&self, value: &str) -> usize {
+        value
+            .bytes()
+            .map(|byte| {
+                if self.leaves_as_is(byte) || (byte == b' ' && *self == EncodeSet::FormUrlEncoded) {
+                    1
+                } else {
+                    3
+                }
+            })
+            .sum()
+    }
+
+    /// Whether `byte` is written as-is by this set.
+    fn leaves_as_is(&self, byte: u8) -> bool {
+        byte.is_ascii_alphanumeric()
+            || match self {
+                EncodeSet::QueryLenient => b"-._~!$'()*,;:@/?".contains(&byte),
+                EncodeSet::Rfc3986Strict | EncodeSet::OAuth1 => b"-._~".contains(&byte),
+                EncodeSet::FormUrlEncoded => b"*-._".contains(&byte),
+            }
+    }
+
+    /// Percent-encodes `value` according to this set, writing it straight into `w`. Runs of
+    /// characters that are left as-is are written as a single `str`.
+    pub(crate) fn encode_into(&self, value: &str, w: &mut dyn fmt::Write) -> fmt::Result {
+        let mut last = 0;
+
+        for (i, byte) in value.bytes().enumerate() {
+            if self.leaves_as_is(byte) {
+                continue;
+            }
+
+            // runs left as-is are ASCII, so they always start and end on a char boundary
+            if last < i {
+                w.write_str(&value[last..i])?;
+            }
+            last = i + 1;
+
+            if byte == b' ' && *self == EncodeSet::FormUrlEncoded {
+                w.write_char('+')?;
+            } else {
+                write!(w, "%{byte:02X}")?;
+            }
+        }
+
+        w.write_str(&value[last..])
+    }
+}
+
+/// Encodes `value` following the `application/x-www-form-urlencoded` serializer: ASCII
+/// alphanumerics and `*-._` are left as-is, spaces become `+`, and every other byte is
+/// percent-encoded.
+pub(crate) fn form_encode(value: &str) -> String {
+    EncodeSet::FormUrlEncoded.encode(value)
+}
+
+/// Rewrites every valid percent-escape in `value` to use uppercase hex digits, e.g. `%2f` to `%2F`.
+/// Everything else is left untouched, including `%` characters that don't start a valid escape.
+pub(crate) fn normalize_percent_escapes(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut normalized = String::with_capacity(value.len());
+    let mut last = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && bytes[i + 1].is_ascii_hexdigit()
+            && bytes[i + 2].is_ascii_hexdigit()
+        {
+            normalized.push_str(&value[last..i]);
+            normalized.push('%');
+            normalized.push(bytes[i + 1].to_ascii_uppercase() as char);
+            normalized.push(bytes[i + 2].to_ascii_uppercase() as char);
+            i += 3;
+            last = i;
+        } else {
+            i += 1;
+        }
+    }
+
+    normalized.push_str(&value[last..]);
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_form_encode() {
+        assert_eq!(form_encode("a b"), "a+b");
+        assert_eq!(form_encode("a+b"), "a%2Bb");
+        assert_eq!(form_encode("*-._~"), "*-._%7E");
+        assert_eq!(form_encode("key=value&other"), "key%3Dvalue%26other");
+        assert_eq!(form_encode("café"), "caf%C3%A9");
+    }
+
+    #[test]
+    fn test_normalize_percent_escapes() {
+        assert_eq!(normalize_percent_escapes("a%2fb%2Fc%3a"), "a%2Fb%2Fc%3A");
+        assert_eq!(normalize_percent_escapes("100%"), "100%");
+        assert_eq!(normalize_percent_escapes("%zz%2"), "%zz%2");
+        assert_eq!(normalize_percent_escapes("%%2f"), "%%2F");
+        assert_eq!(normalize_percent_escapes("caf%c3%a9 é"), "caf%C3%A9 é");
+    }
+
+    #[test]
+    fn test_encode_sets() {
+        let value = "!*'()~ /?:";
+
+        assert_eq!(EncodeSet::QueryLenient.encode(value), "!*'()~%20/?:");
+        assert_eq!(
+            EncodeSet::Rfc3986Strict.encode(value),
+            "%21%2A%27%28%29~%20%2F%3F%3A"
+        );
+        assert_eq!(
+            EncodeSet::FormUrlEncoded.encode(value),
+            "%21*%27%28%29%7E+%2F%3F%3A"
+        );
+    }
+
+    #[test]
+    fn test_oauth1_encode_set() {
+        assert_eq!(EncodeSet::OAuth1.encode("a b+c~"), "a%20b%2Bc~");
+        assert_eq!(EncodeSet::OAuth1.encode("!*'()"), "%21%2A%27%28%29");
+        assert_eq!(EncodeSet::OAuth1.encode("=%3D"), "%3D%253D");
+        assert_eq!(EncodeSet::OAuth1.encode("☃"), "%E2%98%83");
+    }
+
+    #[test]
+    fn test_encode_sets_reserved_separators() {
+        let value = "a&b=c+d#e%f";
+
+        for set in [
+            EncodeSet::QueryLenient,
+            EncodeSet::Rfc3986Strict,
+            EncodeSet::FormUrlEncoded,
+            EncodeSet::OAuth1,
+        ] {
+            assert_eq!(set.encode(value), "a%26b%3Dc%2Bd%23e%25f");
+        }
+    }
+
+    #[test]
+    fn test_rfc3986_strict_matches_urlencoding() {
+        let value = "!*'()~ /?:&=+#%[]@$,;café";
+
+        assert_eq!(
+            EncodeSet::Rfc3986Strict.encode(value),
+            urlencoding::encode(value)
+        );
+    }
+}
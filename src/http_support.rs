@@ -0,0 +1,161 @@
+//! Integration with [`http`](https://crates.io/crates/http) request builders, as used by Hyper.
+use crate::ToQueryParams;
+
+/// [`QueryRequestBuilderExt`] adds `query` to `http::request::Builder`, appending the url-encoded
+/// query string from [`ToQueryParams::to_query_string`] to the builder's Uri.
+///
+/// It's implemented with the `http` feature enabled, which `hyper` enables too, and is also
+/// exported as `WithQuery`.
+pub trait QueryRequestBuilderExt: Sized {
+    /// Appends the query parameters produced by `params` to the request's Uri, keeping any query
+    /// it already has. Set the Uri before calling this, since a later `uri` call replaces it.
+    ///
+    /// The builder is returned unchanged when `params` has no pairs. A builder whose Uri was never
+    /// set has http's default Uri of `/`, so the query is appended to that, e.g. `/?page=2`.
+    fn query<T: ToQueryParams + ?Sized>(self, params: &T) -> Self;
+
+    /// The same as `query`, for code reading better with a `with_` prefix.
+    fn with_query<T: ToQueryParams + ?Sized>(self, params: &T) -> Self {
+        self.query(params)
+    }
+}
+
+impl QueryRequestBuilderExt for http::request::Builder {
+    fn query<T: ToQueryParams + ?Sized>(self, params: &T) -> Self {
+        // the builder has already failed, and keeps its first error
+        let Some(uri) = self.uri_ref().cloned() else {
+            return self;
+        };
+
+        if !params.has_params() {
+            return self;
+        }
+
+        match params.apply_to_uri(uri.clone()) {
+            Ok(uri) => self.uri(uri),
+            // the builder only records errors from its own conversions, so it's handed the
+            // appended Uri as a string to reject
+            Err(_) => self.uri(params.to_url(&uri.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::QueryParams;
+    use http::{Method, Request};
+
+    #[derive(QueryParams)]
+    struct TestItem {
+        #[query(required)]
+        a: String,
+        b: Option<i32>,
+    }
+
+    #[derive(QueryParams)]
+    struct TestItemOptional {
+        a: Option<u32>,
+    }
+
+    #[derive(QueryParams)]
+    struct TestItemRaw {
+        #[query(required, raw)]
+        a: String,
+    }
+
+    #[test]
+    fn test_request_builder_query() {
+        let test_item = TestItem {
+            a: "please encode me".to_string(),
+            b: Some(1),
+        };
+
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri("https://api.example.com/items?page=2")
+            .query(&test_item)
+            .body(())
+            .unwrap();
+
+        assert_eq!(
+            request.uri().query(),
+            Some("page=2&a=please%20encode%20me&b=1")
+        );
+        assert_eq!(request.uri().host(), Some("api.example.com"));
+        assert_eq!(request.uri().path(), "/items");
+
+        let request = Request::builder()
+            .uri("/items")
+            .query(&test_item)
+            .body(())
+            .unwrap();
+
+        assert_eq!(request.uri(), "/items?a=please%20encode%20me&b=1");
+    }
+
+    #[test]
+    fn test_request_builder_with_query() {
+        let test_item = TestItem {
+            a: "a b".to_string(),
+            b: None,
+        };
+
+        let request = Request::builder()
+            .uri("https://api.example.com/items?page=2")
+            .with_query(&test_item)
+            .body(())
+            .unwrap();
+
+        assert_eq!(request.uri().query(), Some("page=2&a=a%20b"));
+
+        let request = Request::builder().with_query(&test_item).body(()).unwrap();
+
+        assert_eq!(request.uri(), "/?a=a%20b");
+    }
+
+    #[test]
+    fn test_request_builder_query_empty() {
+        let test_item = TestItemOptional { a: None };
+
+        let request = Request::builder()
+            .uri("https://api.example.com/items?page=2")
+            .with_query(&test_item)
+            .body(())
+            .unwrap();
+
+        assert_eq!(request.uri(), "https://api.example.com/items?page=2");
+
+        let request = Request::builder()
+            .uri("/items")
+            .query(&test_item)
+            .body(())
+            .unwrap();
+
+        assert_eq!(request.uri(), "/items");
+    }
+
+    #[test]
+    fn test_request_builder_query_invalid() {
+        let test_item = TestItemRaw {
+            a: "not encoded".to_string(),
+        };
+
+        let request = Request::builder()
+            .uri("https://api.example.com/items")
+            .query(&test_item)
+            .body(());
+
+        assert!(request.is_err());
+
+        let request = Request::builder()
+            .uri("https://exa mple.com")
+            .query(&TestItem {
+                a: "a".to_string(),
+                b: None,
+            })
+            .body(());
+
+        assert!(request.is_err());
+    }
+}
@@ -6,140 +6,4901 @@ pub use query_params_macro::QueryParams;
 #[doc(hidden)]
 pub use urlencoding;
 
+#[cfg(feature = "base64")]
+#[doc(hidden)]
+pub use base64;
+
+#[cfg(feature = "serde_json")]
+#[doc(hidden)]
+pub use serde_json;
+
+#[cfg(feature = "time")]
+#[doc(hidden)]
+pub use time;
+
+#[cfg(feature = "utoipa")]
+#[doc(hidden)]
+pub use utoipa;
+
 extern crate self as to_query_params;
 
-/// [`ToQueryParams`] contains two methods, `to_query_params` and `to_encoded_params`, which each
-/// produce a `Vec<(String, String)>` representing the struct as query parameters, either un-encoded
-/// or url-encoded respectively.
-///
-pub trait ToQueryParams {
-    /// Creates a `Vec<(String, String)>` as the un-encoded (key, value) pairs for query parameters.
-    fn to_query_params(&self) -> Vec<(String, String)>;
+#[doc(hidden)]
+pub mod __private;
+mod builder;
+pub use builder::QueryParamsBuilder;
+mod encoding;
+pub use encoding::EncodeSet;
+#[cfg(feature = "http")]
+mod http_support;
+#[cfg(feature = "http")]
+pub use http_support::{QueryRequestBuilderExt, QueryRequestBuilderExt as WithQuery};
+#[cfg(feature = "reqwest")]
+mod reqwest_support;
+#[cfg(feature = "reqwest")]
+pub use reqwest_support::RequestBuilderExt;
+#[cfg(feature = "awc")]
+mod awc_support;
+#[cfg(feature = "awc")]
+pub use awc_support::ClientRequestExt;
+#[cfg(feature = "ureq")]
+mod ureq_support;
+#[cfg(feature = "ureq")]
+pub use ureq_support::UreqRequestExt;
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+/// [`ToQueryParams`] contains two methods, `to_query_params` and `to_encoded_params`, which each
+/// produce a `Vec<(String, String)>` representing the struct as query parameters, either un-encoded
+/// or url-encoded respectively.
+///
+/// The remaining methods are provided helpers built on top of those two.
+///
+/// # Ordering
+/// Derived implementations produce pairs in the order the struct's fields are declared, whether
+/// they're required or optional. Fields marked `#[query(order = N)]` are moved ahead of the rest,
+/// in ascending order, and `#[query(sort)]` on the struct sorts the output instead. Fields emitting
+/// several pairs, like `nested` fields, produce them together at the field's position.
+///
+/// # Query vs. Form Encoding
+/// `to_encoded_params` percent-encodes values for use in a URL's query string, so a space becomes
+/// `%20`. `to_form_encoded_params` instead follows the `application/x-www-form-urlencoded` rules
+/// used for form bodies, where a space becomes `+` and a literal `+` must be encoded as `%2B`.
+/// Use form encoding for request bodies, and query encoding for URLs.
+pub trait ToQueryParams {
+    /// Creates a `Vec<(String, String)>` as the un-encoded (key, value) pairs for query parameters.
+    fn to_query_params(&self) -> Vec<(String, String)>;
+
+    /// Creates a `Vec<(String, String)>` as the url-encoded (key, value) pairs for query parameters.
+    fn to_encoded_params(&self) -> Vec<(String, String)>;
+
+    /// Appends the un-encoded (key, value) pairs from `to_query_params` to `out`, leaving its
+    /// existing entries in place. Derived implementations push directly into `out`, avoiding an
+    /// intermediate `Vec`, and implement `to_query_params` on top of this.
+    fn extend_params(&self, out: &mut Vec<(String, String)>) {
+        out.extend(self.to_query_params());
+    }
+
+    /// Creates the same un-encoded pairs as `to_query_params`, as `Cow`s. Derived implementations
+    /// borrow the keys, and the values of `String` fields that are emitted as-is, only allocating
+    /// for values that need formatting. The default implementation owns everything.
+    fn to_query_params_cow(&self) -> Vec<(Cow<'static, str>, Cow<'_, str>)> {
+        self.to_query_params()
+            .into_iter()
+            .map(|(key, value)| (Cow::Owned(key), Cow::Owned(value)))
+            .collect()
+    }
+
+    /// The number of pairs `to_query_params` would create. Derived implementations count them from
+    /// the fields directly, without building a `Vec` or formatting any values, and count `nested`
+    /// and `indexed` fields with the child's own `params_len`.
+    fn params_len(&self) -> usize {
+        self.to_query_params().len()
+    }
+
+    /// Whether `to_query_params` would create any pairs at all, e.g. to decide whether a URL needs
+    /// a `?`.
+    fn has_params(&self) -> bool {
+        self.params_len() > 0
+    }
+
+    /// Creates the same un-encoded pairs as `to_query_params`, but with the values of
+    /// `#[query(sensitive)]` fields replaced by a placeholder, for logging requests without leaking
+    /// secrets. The default implementation has no sensitive fields, so it's `to_query_params`.
+    fn to_redacted_params(&self) -> Vec<(String, String)> {
+        self.to_query_params()
+    }
+
+    /// Creates the un-encoded pairs from `to_query_params`, replacing the value of every pair whose
+    /// key is in `keys` with `***`, for secrets that are only known at runtime. Keys are compared
+    /// case-sensitively after renaming, and the values of other pairs are left in place.
+    fn to_masked_params(&self, keys: &[&str]) -> Vec<(String, String)> {
+        let mut query_params = self.to_query_params();
+
+        for (key, value) in query_params.iter_mut() {
+            if keys.contains(&key.as_str()) {
+                *value = String::from("***");
+            }
+        }
+
+        query_params
+    }
+
+    /// Consumes `self` to create the same un-encoded pairs as `to_query_params`. Derived
+    /// implementations move owned `String` fields into the output instead of cloning them.
+    fn into_query_params(self) -> Vec<(String, String)>
+    where
+        Self: Sized,
+    {
+        self.to_query_params()
+    }
+
+    /// Creates the un-encoded (key, value) pairs as in `to_query_params`, but returns a
+    /// [`TooManyParams`] error if more than `max` pairs would be produced.
+    fn to_query_params_capped(&self, max: usize) -> Result<Vec<(String, String)>, TooManyParams> {
+        let query_params = self.to_query_params();
+
+        if query_params.len() > max {
+            return Err(TooManyParams {
+                count: query_params.len(),
+                max,
+            });
+        }
+
+        Ok(query_params)
+    }
+
+    /// Creates the un-encoded pairs from `to_query_params`, leaving out those whose key is in
+    /// `skip`. Keys are compared after renaming, and a `nested` field's pairs are matched by their
+    /// full key, e.g. `address[city]`.
+    fn to_query_params_excluding(&self, skip: &[&str]) -> Vec<(String, String)> {
+        let mut query_params = self.to_query_params();
+        query_params.retain(|(key, _)| !skip.contains(&key.as_str()));
+        query_params
+    }
+
+    /// Creates the un-encoded pairs from `to_query_params` with every key appearing once, resolving
+    /// duplicates according to `strategy`. Pairs with unique keys are left as they are.
+    fn to_unique_params(&self, strategy: DuplicateStrategy) -> Vec<(String, String)> {
+        let query_params = self.to_query_params();
+
+        match strategy {
+            DuplicateStrategy::KeepFirst => keep_first(query_params),
+            DuplicateStrategy::KeepLast => {
+                let mut query_params = keep_first(query_params.into_iter().rev());
+                query_params.reverse();
+                query_params
+            }
+            DuplicateStrategy::JoinWith(separator) => {
+                let mut joined: Vec<(String, String)> = Vec::with_capacity(query_params.len());
+                let mut positions: HashMap<String, usize> = HashMap::new();
+
+                for (key, value) in query_params {
+                    if let Some(&position) = positions.get(&key) {
+                        let joined_value = &mut joined[position].1;
+                        joined_value.push(separator);
+                        joined_value.push_str(&value);
+                    } else {
+                        positions.insert(key.clone(), joined.len());
+                        joined.push((key, value));
+                    }
+                }
+
+                joined
+            }
+        }
+    }
+
+    /// Creates the (key, value) pairs according to options chosen at runtime. The pairs come from
+    /// `to_encoded_params` if `opts.encode` is set, or `to_query_params` otherwise. The prefix is
+    /// then prepended to every key, encoded along with it, before the pairs are sorted.
+    fn to_query_params_with(&self, opts: &QueryOptions) -> Vec<(String, String)> {
+        let mut query_params = if opts.encode {
+            self.to_encoded_params()
+        } else {
+            self.to_query_params()
+        };
+
+        if let Some(prefix) = &opts.prefix {
+            let prefix = if opts.encode {
+                urlencoding::encode(prefix)
+            } else {
+                prefix.into()
+            };
+
+            for (key, _) in query_params.iter_mut() {
+                key.insert_str(0, &prefix);
+            }
+        }
+
+        if opts.sort {
+            query_params.sort();
+        }
+
+        query_params
+    }
+
+    /// Collects the un-encoded pairs from `to_query_params` into a `HashMap`. If a key appears more
+    /// than once, the last pair with that key wins.
+    fn to_hashmap(&self) -> HashMap<String, String> {
+        self.to_query_params().into_iter().collect()
+    }
+
+    /// Collects the un-encoded pairs from `to_query_params` into a `BTreeMap`, which iterates in key
+    /// order. If a key appears more than once, the last pair with that key wins.
+    fn to_btreemap(&self) -> BTreeMap<String, String> {
+        self.to_query_params().into_iter().collect()
+    }
+
+    /// Collects the un-encoded pairs from `to_query_params` into an `IndexMap`, which iterates in
+    /// the order the keys first appeared. If a key appears more than once, the last pair's value
+    /// wins, but the key keeps the position of its first appearance.
+    #[cfg(feature = "indexmap")]
+    fn to_indexmap(&self) -> indexmap::IndexMap<String, String> {
+        self.to_query_params().into_iter().collect()
+    }
+
+    /// Creates a `Vec<(String, String)>` as the (key, value) pairs percent-encoded with the chosen
+    /// [`EncodeSet`]. Encoding is applied to the un-encoded pairs from `to_query_params`.
+    fn to_encoded_params_with(&self, set: EncodeSet) -> Vec<(String, String)> {
+        self.to_query_params()
+            .iter()
+            .map(|(key, value)| (set.encode(key), set.encode(value)))
+            .collect()
+    }
+
+    /// Creates the url-encoded pairs as in `to_encoded_params`, with every percent-escape rewritten
+    /// to use uppercase hex digits (`%2F` rather than `%2f`). This normalizes values that were
+    /// already encoded elsewhere, like `raw` fields, without encoding them again. A `%` that doesn't
+    /// start a valid escape is left as-is.
+    fn to_normalized_params(&self) -> Vec<(String, String)> {
+        self.to_encoded_params()
+            .iter()
+            .map(|(key, value)| {
+                (
+                    encoding::normalize_percent_escapes(key),
+                    encoding::normalize_percent_escapes(value),
+                )
+            })
+            .collect()
+    }
+
+    /// Creates a `Vec<(String, String)>` as the (key, value) pairs encoded with
+    /// `application/x-www-form-urlencoded` rules, where spaces are encoded as `+`.
+    fn to_form_encoded_params(&self) -> Vec<(String, String)> {
+        self.to_query_params()
+            .iter()
+            .map(|(key, value)| (encoding::form_encode(key), encoding::form_encode(value)))
+            .collect()
+    }
+
+    /// Creates an `application/x-www-form-urlencoded` body (`key=value&key2=value2`) from the
+    /// un-encoded pairs, suitable for a form POST body.
+    fn to_form_body(&self) -> String {
+        self.to_form_encoded_params()
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect::<Vec<String>>()
+            .join("&")
+    }
+
+    /// Creates a canonical query string for request signing, like AWS SigV4's: keys and values are
+    /// encoded with [`EncodeSet::Rfc3986Strict`], sorted by encoded key and then by encoded value,
+    /// and joined as `key=value&key2=value2`. Empty values still produce a trailing `=`. The result
+    /// doesn't depend on the order of the struct's fields.
+    fn to_canonical_query_string(&self) -> String {
+        let mut encoded_params = self.to_encoded_params_with(EncodeSet::Rfc3986Strict);
+        encoded_params.sort();
+
+        encoded_params
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect::<Vec<String>>()
+            .join("&")
+    }
+
+    /// Creates the url-encoded query string (`key=value&key2=value2`) from `write_query_string`,
+    /// without a leading `?`. Empty values are written as `key=`, and no parameters produce an
+    /// empty string.
+    fn to_query_string(&self) -> String {
+        let mut query_string = String::new();
+        self.write_query_string(&mut query_string)
+            .expect("writing to a String can't fail");
+        query_string
+    }
+
+    /// Creates the url-encoded query string as in `to_query_string`, returning a
+    /// [`QueryParamsError::TooLong`] with its length instead if it's longer than `max_len` bytes.
+    fn try_to_query_string(&self, max_len: usize) -> Result<String, QueryParamsError> {
+        let query_string = self.to_query_string();
+
+        if query_string.len() > max_len {
+            return Err(QueryParamsError::TooLong {
+                length: query_string.len(),
+                max: max_len,
+            });
+        }
+
+        Ok(query_string)
+    }
+
+    /// The maximum length of the url-encoded query string in bytes, if the type has one. Derived
+    /// implementations return the limit from `#[query(max_length = N)]`.
+    fn max_query_length(&self) -> Option<usize> {
+        None
+    }
+
+    /// Creates the url-encoded query string as in `try_to_query_string`, checked against
+    /// `max_query_length`. Types without a maximum length always succeed.
+    fn try_to_query_string_default(&self) -> Result<String, QueryParamsError> {
+        match self.max_query_length() {
+            Some(max_len) => self.try_to_query_string(max_len),
+            None => Ok(self.to_query_string()),
+        }
+    }
+
+    /// Appends the query string from `to_query_string` to `base`, using `?` if `base` has no query
+    /// yet, or `&` if it does. A trailing `?` or `&` on `base` is reused, and a `#fragment` stays at
+    /// the end. `base` is otherwise left untouched, and is returned unchanged when there are no
+    /// parameters.
+    fn to_url(&self, base: &str) -> String {
+        let query_string = self.to_query_string();
+
+        if query_string.is_empty() {
+            return base.to_string();
+        }
+
+        let (url, fragment) = base.split_at(base.find('#').unwrap_or(base.len()));
+
+        let separator = if url.ends_with('?') || url.ends_with('&') {
+            ""
+        } else if url.contains('?') {
+            "&"
+        } else {
+            "?"
+        };
+
+        format!("{url}{separator}{query_string}{fragment}")
+    }
+
+    /// Appends the query string to `base` as in `to_url`, and parses the result as a
+    /// `hyper::Uri`.
+    #[cfg(feature = "hyper")]
+    fn build_uri(&self, base: &str) -> Result<hyper::Uri, hyper::http::uri::InvalidUri> {
+        self.to_url(base).parse()
+    }
+
+    /// Appends the query string to the query of `uri`, using `&` if it already has one, and
+    /// rebuilds it. The scheme, authority, path and existing query are kept as they are, and `uri`
+    /// is returned unchanged when there are no parameters.
+    #[cfg(feature = "http")]
+    fn apply_to_uri(&self, uri: http::Uri) -> Result<http::Uri, http::Error> {
+        let query_string = self.to_query_string();
+
+        if query_string.is_empty() {
+            return Ok(uri);
+        }
+
+        let mut parts = uri.into_parts();
+        let (path, query) = match &parts.path_and_query {
+            Some(path_and_query) => (path_and_query.path(), path_and_query.query()),
+            None => ("/", None),
+        };
+
+        let path_and_query = match query {
+            Some(query) if query.is_empty() || query.ends_with('&') => {
+                format!("{path}?{query}{query_string}")
+            }
+            Some(query) => format!("{path}?{query}&{query_string}"),
+            None => format!("{path}?{query_string}"),
+        };
+
+        parts.path_and_query = Some(path_and_query.parse()?);
+
+        Ok(http::Uri::from_parts(parts)?)
+    }
+
+    /// Appends the query string to `path` as in `to_url`, and parses the result as an
+    /// `http::uri::PathAndQuery`, for clients that take the authority from the connection. A `/` is
+    /// added to the start of `path` if it's missing, so `items` becomes `/items`, and an empty
+    /// `path` becomes `/`. With no parameters, the result is just the path.
+    #[cfg(feature = "http")]
+    fn to_path_and_query(
+        &self,
+        path: &str,
+    ) -> Result<http::uri::PathAndQuery, http::uri::InvalidUri> {
+        let path = if path.starts_with('/') {
+            Cow::Borrowed(path)
+        } else {
+            Cow::Owned(format!("/{path}"))
+        };
+
+        self.to_url(&path).parse()
+    }
+
+    /// Appends the un-encoded pairs from `to_query_params` to the query of `url`, after any pairs
+    /// it already has. `url` encodes them itself, as `application/x-www-form-urlencoded`, so they're
+    /// encoded exactly once. `url` is left untouched when there are no parameters.
+    #[cfg(feature = "url")]
+    fn append_to_url(&self, url: &mut url::Url) {
+        let query_params = self.to_query_params();
+
+        // `query_pairs_mut` adds an empty query to a Url without one, even if nothing is appended
+        if query_params.is_empty() {
+            return;
+        }
+
+        url.query_pairs_mut().extend_pairs(query_params);
+    }
+
+    /// Creates a `web_sys::UrlSearchParams` holding the un-encoded pairs from `to_query_params`,
+    /// leaving the encoding to the browser. Only available on `wasm32` targets.
+    #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+    fn to_url_search_params(&self) -> web_sys::UrlSearchParams {
+        let search_params =
+            web_sys::UrlSearchParams::new().expect("creating an empty UrlSearchParams can't fail");
+
+        for (key, value) in self.to_query_params() {
+            search_params.append(&key, &value);
+        }
+
+        search_params
+    }
+
+    /// Clears `buf` and writes the url-encoded query string (`key=value&key2=value2`) into it,
+    /// allowing a single buffer to be reused across many calls.
+    fn write_query_string_into(&self, buf: &mut String) {
+        buf.clear();
+        self.write_query_string(buf)
+            .expect("writing to a String can't fail");
+    }
+
+    /// Writes the url-encoded query string (`key=value&key2=value2`) into `w`, after anything it
+    /// already holds. Derived implementations percent-encode each key and value straight into `w`,
+    /// without building the encoded pairs or the query string first, unless the struct is
+    /// `#[query(sort)]`. The default implementation writes the pairs from `to_encoded_params`.
+    ///
+    /// `w` is a trait object so the method can be called on `dyn ToQueryParams`, and any
+    /// `&mut impl fmt::Write` can be passed as-is.
+    fn write_query_string(&self, w: &mut dyn fmt::Write) -> fmt::Result {
+        write_pairs(w, &self.to_encoded_params())
+    }
+}
+
+/// Writes `pairs` into `w` as `key=value`, separated by `&`.
+fn write_pairs<'a, W: fmt::Write + ?Sized>(
+    w: &mut W,
+    pairs: impl IntoIterator<Item = &'a (String, String)>,
+) -> fmt::Result {
+    for (i, (key, value)) in pairs.into_iter().enumerate() {
+        if i > 0 {
+            w.write_char('&')?;
+        }
+        w.write_str(key)?;
+        w.write_char('=')?;
+        w.write_str(value)?;
+    }
+
+    Ok(())
+}
+
+/// [`TryToQueryParams`] creates the un-encoded (key, value) pairs like
+/// [`ToQueryParams::to_query_params`], but returns an error instead of panicking when a field can't
+/// be serialized.
+///
+/// [`QueryParams`] implements this for every derived struct, returning errors from `json` fields
+/// and from the functions given to `#[query(with_try = "...")]`.
+///
+/// ```
+/// # use to_query_params::{QueryParams, TryToQueryParams};
+/// fn page_size(size: &u32) -> Result<String, String> {
+///     if *size > 100 {
+///         return Err(format!("{size} is over the limit of 100"));
+///     }
+///     Ok(size.to_string())
+/// }
+///
+/// #[derive(QueryParams)]
+/// struct Page {
+///     #[query(required, with_try = "page_size")]
+///     size: u32,
+/// }
+///
+/// let error = Page { size: 500 }.try_to_query_params().unwrap_err();
+///
+/// assert_eq!(error.field(), Some("size"));
+/// assert_eq!(
+///     error.to_string(),
+///     "query field `size` failed to serialize: 500 is over the limit of 100"
+/// );
+/// ```
+pub trait TryToQueryParams {
+    /// Creates a `Vec<(String, String)>` as the un-encoded (key, value) pairs for query parameters,
+    /// or the error from the first field that failed to serialize.
+    fn try_to_query_params(&self) -> Result<Vec<(String, String)>, QueryParamsError>;
+}
+
+/// [`QueryParamsError`] is returned by [`TryToQueryParams::try_to_query_params`] when a field's
+/// value couldn't be serialized, or failed one of its validation attributes.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum QueryParamsError {
+    /// The field's value couldn't be serialized, e.g. by a `json` or `with_try` field.
+    Serialize {
+        /// The name of the field that failed to serialize.
+        field: &'static str,
+        /// The error returned while serializing the field.
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// The field's value failed a validation attribute like `min`, `max`, `non_empty` or
+    /// `pattern`.
+    Invalid {
+        /// The name of the invalid field.
+        field: &'static str,
+        /// Why the value is invalid, e.g. `must be at least 1`.
+        reason: String,
+    },
+    /// More than one field of a `#[query(exclusive(...))]` group was set.
+    Exclusive {
+        /// The names of the fields that were set, in the order the group lists them.
+        fields: Vec<&'static str>,
+    },
+    /// The url-encoded query string is longer than allowed.
+    TooLong {
+        /// The length of the query string, in bytes.
+        length: usize,
+        /// The maximum length that was allowed, in bytes.
+        max: usize,
+    },
+}
+
+impl QueryParamsError {
+    /// Creates a [`QueryParamsError::Serialize`] for the field named `field`, caused by `source`.
+    pub fn new(field: &'static str, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        QueryParamsError::Serialize {
+            field,
+            source: source.into(),
+        }
+    }
+
+    /// The name of the field that caused the error, or the first of the fields that were set
+    /// together for [`QueryParamsError::Exclusive`]. [`QueryParamsError::TooLong`] isn't caused by
+    /// any one field, so it has none.
+    pub fn field(&self) -> Option<&'static str> {
+        match self {
+            QueryParamsError::Serialize { field, .. } | QueryParamsError::Invalid { field, .. } => {
+                Some(field)
+            }
+            QueryParamsError::Exclusive { fields } => fields.first().copied(),
+            QueryParamsError::TooLong { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for QueryParamsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryParamsError::Serialize { field, source } => {
+                write!(f, "query field `{field}` failed to serialize: {source}")
+            }
+            QueryParamsError::Invalid { field, reason } => {
+                write!(f, "query field `{field}` is invalid: {reason}")
+            }
+            QueryParamsError::Exclusive { fields } => {
+                let fields: Vec<String> = fields.iter().map(|field| format!("`{field}`")).collect();
+                write!(
+                    f,
+                    "query fields {} can't be set together",
+                    fields.join(", ")
+                )
+            }
+            QueryParamsError::TooLong { length, max } => {
+                write!(
+                    f,
+                    "query string is {length} bytes long, but at most {max} are allowed"
+                )
+            }
+        }
+    }
+}
+
+impl Error for QueryParamsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            QueryParamsError::Serialize { source, .. } => Some(&**source),
+            QueryParamsError::Invalid { .. }
+            | QueryParamsError::Exclusive { .. }
+            | QueryParamsError::TooLong { .. } => None,
+        }
+    }
+}
+
+/// Static information about the query parameters a type can produce, available without an instance.
+///
+/// [`QueryParams`] implements this for every derived struct, returning the same keys as its
+/// `QUERY_KEYS` constant: the post-rename keys in declaration order, without excluded fields.
+/// Keys of `nested`, `indexed`, `flatten` and `value_object` fields are only known at runtime, so
+/// they're omitted.
+///
+/// ```
+/// # use to_query_params::{QueryParams, QueryParamsMeta};
+/// #[derive(QueryParams)]
+/// struct Search {
+///     #[query(required, rename = "q")]
+///     term: String,
+///     limit: Option<u32>,
+///     #[query(exclude)]
+///     cache_key: String,
+/// }
+///
+/// assert_eq!(Search::param_names(), &["q", "limit"]);
+/// ```
+pub trait QueryParamsMeta {
+    /// The un-encoded keys that can be emitted as query parameters, in declaration order.
+    fn param_names() -> &'static [&'static str];
+}
+
+/// Concatenates the un-encoded pairs from `to_query_params` for each of `sources`, in order.
+/// Keys from later sources are appended as-is, so duplicate keys are kept.
+///
+/// ```
+/// # use to_query_params::{merge_query_params, QueryParams, ToQueryParams};
+/// #[derive(QueryParams)]
+/// struct Config {
+///     #[query(required)]
+///     api_key: String,
+/// }
+///
+/// #[derive(QueryParams)]
+/// struct Search {
+///     #[query(required)]
+///     term: String,
+/// }
+///
+/// let config = Config { api_key: "abc".into() };
+/// let search = Search { term: "shoes".into() };
+///
+/// let expected: Vec<(String, String)> = vec![
+///     ("api_key".into(), "abc".into()),
+///     ("term".into(), "shoes".into()),
+/// ];
+///
+/// assert_eq!(merge_query_params([&config as &dyn ToQueryParams, &search]), expected);
+/// ```
+pub fn merge_query_params<'a>(
+    sources: impl IntoIterator<Item = &'a dyn ToQueryParams>,
+) -> Vec<(String, String)> {
+    let mut query_params = Vec::new();
+
+    for source in sources {
+        source.extend_params(&mut query_params);
+    }
+
+    query_params
+}
+
+/// Asserts that two collections of (key, value) pairs hold the same pairs, ignoring their order
+/// but not how often each pair appears. Keys and values can be anything implementing `ToString`,
+/// so the output of `to_query_params` can be compared to an array of `&str` pairs. On a mismatch,
+/// both sides are shown sorted, so the diff lines up.
+///
+/// Derived output is in declaration order, so this is for tests that shouldn't depend on it, like
+/// ones covering `QueryParamsBuilder`s or hand-written implementations.
+///
+/// ```
+/// # use to_query_params::{assert_query_eq, QueryParams, ToQueryParams};
+/// #[derive(QueryParams)]
+/// struct Search {
+///     #[query(required)]
+///     term: String,
+///     #[query(required)]
+///     page: u32,
+/// }
+///
+/// let search = Search { term: "shoes".into(), page: 2 };
+///
+/// assert_query_eq!(search.to_query_params(), [("page", "2"), ("term", "shoes")]);
+/// ```
+#[macro_export]
+macro_rules! assert_query_eq {
+    ($actual:expr, $expected:expr $(,)?) => {
+        ::std::assert_eq!(
+            $crate::__private::sorted_pairs($actual),
+            $crate::__private::sorted_pairs($expected),
+            "query params differ, ignoring order",
+        )
+    };
+    ($actual:expr, $expected:expr, $($arg:tt)+) => {
+        ::std::assert_eq!(
+            $crate::__private::sorted_pairs($actual),
+            $crate::__private::sorted_pairs($expected),
+            $($arg)+
+        )
+    };
+}
+
+/// Keeps the first pair for each key, in their original order.
+fn keep_first(query_params: impl IntoIterator<Item = (String, String)>) -> Vec<(String, String)> {
+    let mut seen = HashSet::new();
+
+    query_params
+        .into_iter()
+        .filter(|(key, _)| seen.insert(key.clone()))
+        .collect()
+}
+
+/// [`DuplicateStrategy`] selects how [`ToQueryParams::to_unique_params`] resolves pairs that share
+/// a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateStrategy {
+    /// Keeps the first pair with the key, at its position.
+    KeepFirst,
+    /// Keeps the last pair with the key, at its position.
+    KeepLast,
+    /// Joins the values of every pair with the key using the separator, in order, at the position
+    /// of the first pair. E.g. `JoinWith(',')` turns `a=1&a=2` into `a=1,2`.
+    JoinWith(char),
+}
+
+/// [`QueryOptions`] configures [`ToQueryParams::to_query_params_with`] at runtime, as an alternative
+/// to container attributes. The default produces the same pairs as `to_query_params`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryOptions {
+    /// Url-encodes the keys and values, as in `to_encoded_params`.
+    pub encode: bool,
+    /// Sorts the pairs by key, and then by value.
+    pub sort: bool,
+    /// Prepended to every key, e.g. `filter_`.
+    pub prefix: Option<String>,
+}
+
+/// [`TooManyParams`] is returned by [`ToQueryParams::to_query_params_capped`] when more parameters
+/// were produced than the allowed maximum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TooManyParams {
+    /// The number of parameters that were produced.
+    pub count: usize,
+    /// The maximum number of parameters that was allowed.
+    pub max: usize,
+}
+
+impl fmt::Display for TooManyParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} query parameters were produced, but at most {} are allowed",
+            self.count, self.max
+        )
+    }
+}
+
+impl Error for TooManyParams {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::path::{Path, PathBuf};
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItem {
+        #[query(required)]
+        a: i32,
+        #[query(required)]
+        b: i32,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestExcludeItem {
+        #[query(required)]
+        a: i32,
+        #[query(exclude)]
+        b: i32,
+        #[query(exclude)]
+        c: Option<i32>,
+        #[query(required)]
+        d: i32,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestStringItem {
+        #[query(required)]
+        a: String,
+        #[query(required, rename = "please encode")]
+        b: String,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemRequiredRename {
+        #[query(required, rename = "alpha")]
+        a: i32,
+        #[query(required)]
+        b: i32,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemOptionals {
+        a: Option<String>,
+        b: Option<bool>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemRename {
+        #[query(rename = "alpha")]
+        a: Option<String>,
+        #[query(rename = "beta")]
+        b: Option<bool>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemMixedRequiredOptionals {
+        a: Option<String>,
+        b: Option<bool>,
+        #[query(required)]
+        c: i32,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemMixedRequiredOptionalsAndRename {
+        #[query(rename = "alpha")]
+        a: Option<String>,
+        b: Option<bool>,
+        #[query(rename = "gamma")]
+        #[query(required)]
+        c: i32,
+    }
+
+    #[cfg(feature = "serde-compat")]
+    #[derive(QueryParams, serde::Serialize, Debug, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    struct TestItemSerdeRename {
+        #[query(required)]
+        #[serde(rename = "ID")]
+        item_id: i32,
+        #[query(required, rename = "kind")]
+        #[serde(rename = "type")]
+        item_type: String,
+        max_price: Option<i32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min_price: Option<i32>,
+    }
+
+    #[cfg(feature = "serde-compat")]
+    #[derive(QueryParams, serde::Serialize, Debug, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    #[query(rename_all = "kebab-case")]
+    struct TestItemSerdeAndQueryRenameAll {
+        #[serde(rename = "ID")]
+        item_id: Option<i32>,
+        max_price: Option<i32>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemSystemTime {
+        #[query(required)]
+        created: SystemTime,
+        #[query(epoch = "millis")]
+        updated: Option<SystemTime>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemQualifiedPrimitives {
+        #[query(required)]
+        a: core::primitive::i32,
+        b: Option<core::primitive::i32>,
+        c: std::option::Option<std::primitive::bool>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemSkipIf {
+        #[query(required, skip_if = "String::is_empty")]
+        a: String,
+        #[query(skip_if = "is_negative")]
+        b: Option<i32>,
+        #[query(skip_field_if = "is_placeholder")]
+        c: Option<String>,
+    }
+
+    fn is_negative(value: &i32) -> bool {
+        *value < 0
+    }
+
+    fn is_placeholder(value: &Option<String>) -> bool {
+        value.as_deref() == Some("-")
+    }
+
+    #[cfg(feature = "uuid")]
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemUuid {
+        #[query(required)]
+        default: uuid::Uuid,
+        #[query(required, uuid = "hyphenated")]
+        hyphenated: uuid::Uuid,
+        #[query(required, uuid = "simple")]
+        simple: uuid::Uuid,
+        #[query(required, uuid = "urn")]
+        urn: uuid::Uuid,
+        #[query(uuid = "simple")]
+        optional: Option<uuid::Uuid>,
+    }
+
+    #[cfg(feature = "base64")]
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemBase64<'a> {
+        #[query(required, base64)]
+        vec: Vec<u8>,
+        #[query(required, base64)]
+        slice: &'a [u8],
+        #[query(required, base64)]
+        array: [u8; 4],
+        #[query(base64)]
+        optional: Option<Vec<u8>>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemPaths<'a> {
+        #[query(required)]
+        required: PathBuf,
+        #[query(required)]
+        borrowed: &'a Path,
+        optional: Option<PathBuf>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemTransforms {
+        #[query(required, transform = "trim", transform = "lowercase")]
+        a: String,
+        #[query(transform = "lowercase")]
+        #[query(transform = "truncate")]
+        b: Option<String>,
+    }
+
+    fn trim(value: String) -> String {
+        value.trim().to_string()
+    }
+
+    fn lowercase(value: String) -> String {
+        value.to_lowercase()
+    }
+
+    fn truncate(value: String) -> String {
+        value.chars().take(3).collect()
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[derive(serde::Serialize, Debug, PartialEq)]
+    struct TestFilter {
+        status: Vec<String>,
+        min: i32,
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemJson {
+        #[query(required, json)]
+        filter: TestFilter,
+        #[query(json)]
+        tags: Option<Vec<String>>,
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemValueObject {
+        #[query(required)]
+        id: i32,
+        #[query(required, value_object)]
+        params: serde_json::Value,
+        #[query(value_object)]
+        extra: Option<serde_json::Value>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemExcludeIf {
+        #[query(exclude)]
+        debug: bool,
+        #[query(required, exclude_if = "Self::is_production")]
+        trace_id: String,
+        #[query(exclude_if = "Self::is_production")]
+        verbosity: Option<i32>,
+    }
+
+    impl TestItemExcludeIf {
+        fn is_production(&self) -> bool {
+            !self.debug
+        }
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemNonePair {
+        #[query(none_pair("mode" = "default"))]
+        limit: Option<i32>,
+        #[query(rename = "sort order", none_pair("sort order" = "a b"))]
+        sort: Option<String>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    #[query(sort)]
+    struct TestItemSorted {
+        #[query(rename = "zeta")]
+        a: Option<String>,
+        #[query(required)]
+        c: i32,
+        b: Option<bool>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    #[query(sorted, allow_duplicate_keys)]
+    struct TestItemSortedDuplicates {
+        #[query(required, rename = "tag")]
+        second_tag: String,
+        #[query(nested)]
+        address: Option<TestItemAddress>,
+        #[query(rename = "tag")]
+        first_tag: Option<String>,
+        #[query(required, rename = "b")]
+        z: i32,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemRaw {
+        #[query(required, raw)]
+        token: String,
+        #[query(required)]
+        encoded: String,
+        #[query(raw, rename = "next%5Bcursor%5D")]
+        cursor: Option<String>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemEncodeModes {
+        #[query(required, rename = "items[]", encode = "value_only")]
+        value_only: String,
+        #[query(required, rename = "keys[]", encode = "key_only")]
+        key_only: String,
+        #[query(required, rename = "both[]", encode = "both")]
+        both: String,
+        #[query(rename = "none[]", encode = "none")]
+        none: Option<String>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemFormat {
+        #[query(required, format = "{:.2}")]
+        price: f64,
+        #[query(required, format = "{:04}")]
+        padded: i32,
+        #[query(format = "0x{:x}")]
+        hex: Option<u32>,
+        #[query(format = "{{{}}}")]
+        escaped: Option<i32>,
+    }
+
+    #[cfg(feature = "chrono")]
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemChrono {
+        #[query(required)]
+        created: chrono::DateTime<chrono::Utc>,
+        #[query(required)]
+        day: chrono::NaiveDate,
+        #[query(datetime_format = "%Y-%m-%d")]
+        updated: Option<chrono::DateTime<chrono::FixedOffset>>,
+        #[query(datetime_format = "%d/%m/%Y")]
+        due: Option<chrono::NaiveDate>,
+    }
+
+    mod calendar {
+        use std::fmt;
+
+        pub struct DateTime(pub u32);
+
+        impl fmt::Display for DateTime {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "day-{}", self.0)
+            }
+        }
+    }
+
+    // another crate's `DateTime` is emitted with its own `Display`, even with `chrono` enabled
+    #[derive(QueryParams)]
+    struct TestItemOwnDateTime {
+        #[query(required)]
+        created: calendar::DateTime,
+    }
+
+    #[cfg(feature = "time")]
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemTime {
+        #[query(required)]
+        created: time::OffsetDateTime,
+        #[query(required)]
+        day: time::Date,
+        #[query(time_format = "[year]-[month]-[day]")]
+        updated: Option<time::OffsetDateTime>,
+        #[query(time_format = "[day]/[month]/[year]")]
+        due: Option<time::Date>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    #[query(allow_duplicate_keys)]
+    struct TestItemDuplicateKeys {
+        #[query(required, rename = "id")]
+        first: i32,
+        #[query(rename = "id")]
+        second: Option<i32>,
+    }
+
+    // parameters from the example in RFC 5849, section 3.4.1.3.1
+    #[derive(QueryParams, Debug, PartialEq)]
+    #[query(allow_duplicate_keys)]
+    struct TestItemOAuth1 {
+        #[query(required)]
+        b5: String,
+        #[query(required)]
+        a3: String,
+        #[query(required, rename = "c@")]
+        c_at: String,
+        #[query(required)]
+        a2: String,
+        #[query(required)]
+        oauth_consumer_key: String,
+        #[query(required)]
+        oauth_token: String,
+        #[query(required)]
+        oauth_signature_method: String,
+        #[query(required)]
+        oauth_timestamp: String,
+        #[query(required)]
+        oauth_nonce: String,
+        #[query(required)]
+        c2: String,
+        #[query(required, rename = "a3")]
+        a3_second: String,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq, Clone)]
+    struct TestItemOwned {
+        #[query(required)]
+        name: String,
+        #[query(required)]
+        count: i32,
+        #[query(skip_if = "String::is_empty")]
+        note: Option<String>,
+        #[query(exclude_if = "Self::is_anonymous")]
+        email: Option<String>,
+        #[query(rename = "tag", none_pair("tag" = "none"))]
+        label: Option<String>,
+    }
+
+    impl TestItemOwned {
+        fn is_anonymous(&self) -> bool {
+            self.name.is_empty()
+        }
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    #[query(encode = false)]
+    struct TestItemUnencoded {
+        #[query(required, rename = "sort order")]
+        sort: String,
+        #[query(encode = "both")]
+        filter: Option<String>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    #[query(encode = true)]
+    struct TestItemEncoded {
+        #[query(required)]
+        filter: String,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemIpAddrs {
+        #[query(required)]
+        v4: Ipv4Addr,
+        #[query(required)]
+        v6: Ipv6Addr,
+        #[query(required, rename = "bracketed", bracket_ipv6)]
+        bracketed_v6: Ipv6Addr,
+        #[query(bracket_ipv6)]
+        any: Option<IpAddr>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    enum TestSort {
+        #[query(rename = "asc")]
+        Asc,
+        #[query(rename = "desc")]
+        Desc,
+        Relevance,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemEnumValues {
+        #[query(required)]
+        order: TestSort,
+        sort: Option<TestSort>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemOrdered {
+        first_declared: Option<i32>,
+        #[query(required, order = 2)]
+        timestamp: i32,
+        #[query(order = 1)]
+        api_key: Option<String>,
+        #[query(required)]
+        last_declared: bool,
+        #[query(required, order = 10)]
+        signature: String,
+    }
+
+    /// Doc comments and other attributes are left alone.
+    #[derive(QueryParams, serde::Serialize, Debug, PartialEq)]
+    #[non_exhaustive]
+    #[serde(deny_unknown_fields)]
+    struct TestItemOtherAttributes {
+        /// The item's id.
+        #[doc = "Always present."]
+        #[query(required)]
+        #[serde(default)]
+        id: i32,
+        #[cfg(not(test))]
+        #[query(required)]
+        hidden: i32,
+        #[allow(unused)]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemDurations {
+        #[query(required)]
+        timeout: Duration,
+        #[query(duration = "millis")]
+        ttl: Option<std::time::Duration>,
+        #[query(duration = "secs")]
+        delay: Option<core::time::Duration>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq, Clone)]
+    struct TestItemDefaults {
+        #[query(default)]
+        page: Option<u32>,
+        #[query(default)]
+        verbose: Option<bool>,
+        #[query(default, rename = "q")]
+        query: Option<String>,
+        limit: Option<u32>,
+    }
+
+    struct TestItemHandWritten;
+
+    impl ToQueryParams for TestItemHandWritten {
+        fn to_query_params(&self) -> Vec<(String, String)> {
+            vec![("manual".to_string(), "yes".to_string())]
+        }
+
+        fn to_encoded_params(&self) -> Vec<(String, String)> {
+            self.to_query_params()
+        }
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    #[query(rename_all = "camelCase")]
+    struct TestItemCamelCase {
+        id: Option<i32>,
+        user_id: Option<i32>,
+        api_key: Option<i32>,
+        http_url: Option<i32>,
+        ssl_enabled: Option<i32>,
+        page2_size: Option<i32>,
+        #[query(rename = "X-Trace")]
+        trace_id: Option<i32>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    #[query(rename_all = "SCREAMING-KEBAB-CASE")]
+    struct TestItemScreamingKebabCase {
+        #[query(required)]
+        max_price: i32,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    #[query(skip_none = false)]
+    struct TestItemKeepNone {
+        #[query(required)]
+        id: i32,
+        #[query(rename = "q")]
+        query: Option<String>,
+        #[query(default)]
+        page: Option<u32>,
+        #[query(none_pair("sort" = "relevance"))]
+        sort: Option<String>,
+        #[query(nested)]
+        address: Option<TestItemAddress>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    #[query(display)]
+    struct TestItemDisplay<'a, T: ToString>
+    where
+        T: Clone,
+    {
+        #[query(required)]
+        name: &'a str,
+        value: Option<T>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    #[query(into_iterator)]
+    struct TestItemIntoIterator<'a> {
+        #[query(required)]
+        term: &'a str,
+        page: Option<u32>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemResults {
+        #[query(required)]
+        id: i32,
+        count: Result<u32, String>,
+        #[query(rename = "q")]
+        query: std::result::Result<String, ()>,
+        #[query(none_pair("mode" = "fallback"))]
+        mode: Result<String, ()>,
+    }
+
+    #[derive(QueryParams, Debug)]
+    struct TestItemSmartPointers {
+        #[query(required)]
+        name: Arc<String>,
+        #[query(required)]
+        limit: Box<u32>,
+        #[query(required)]
+        ttl: Rc<Duration>,
+        #[query(nested)]
+        address: Option<Arc<TestItemAddress>>,
+        region: Option<Arc<str>>,
+        #[query(skip_if = "String::is_empty")]
+        tag: Option<Rc<String>>,
+    }
+
+    #[derive(QueryParams, Debug)]
+    #[query(key_consts, rename_all = "camelCase")]
+    struct TestItemKeyConsts {
+        #[query(required)]
+        max_price: u32,
+        #[query(rename = "q")]
+        search_term: Option<String>,
+        #[query(exclude)]
+        #[allow(dead_code)]
+        cache_key: String,
+        #[query(nested)]
+        address: Option<TestItemAddress>,
+    }
+
+    #[derive(QueryParams, Debug)]
+    struct TestItemUnwrap {
+        #[query(unwrap, rename = "userId")]
+        user_id: Option<u64>,
+        #[query(unwrap)]
+        session: Option<String>,
+        limit: Option<u32>,
+    }
+
+    fn checked_page_size(size: &u32) -> Result<String, String> {
+        if *size > 100 {
+            return Err(format!("{size} is over the limit of 100"));
+        }
+        Ok(size.to_string())
+    }
+
+    #[derive(QueryParams, Debug)]
+    struct TestItemWithTry {
+        #[query(required, with_try = "checked_page_size", rename = "pageSize")]
+        page_size: u32,
+        #[query(nested)]
+        address: Option<TestItemAddress>,
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[derive(QueryParams, Debug)]
+    struct TestItemJsonFallible {
+        #[query(json)]
+        counts: Option<HashMap<Vec<u8>, u32>>,
+    }
+
+    #[derive(QueryParams, Debug)]
+    struct TestItemRawIdents {
+        #[query(required)]
+        r#type: String,
+        #[query(rename = "matchMode")]
+        r#match: Option<String>,
+        r#ref: Option<u32>,
+    }
+
+    #[derive(QueryParams, Debug)]
+    struct TestItemValidated {
+        #[query(required, min = 1, max = 100)]
+        limit: u32,
+        #[query(non_empty)]
+        term: Option<String>,
+        #[query(min = -1.5)]
+        offset: Option<f64>,
+    }
+
+    #[cfg(feature = "regex")]
+    #[derive(QueryParams, Debug)]
+    struct TestItemPattern {
+        #[query(required, pattern = "^[a-z-]+$")]
+        slug: String,
+    }
+
+    #[derive(QueryParams, Debug)]
+    struct TestItemBoolFormats {
+        #[query(required, bool_format = "1/0")]
+        numeric: bool,
+        #[query(bool_format = "yes/no")]
+        answer: Option<bool>,
+        #[query(bool_format = "on/off")]
+        switch: Option<Box<bool>>,
+        plain: Option<bool>,
+    }
+
+    #[derive(QueryParams, Debug)]
+    #[query(exclusive("cursor", "page"))]
+    #[query(exclusive("since", "until", "window"))]
+    struct TestItemExclusive {
+        cursor: Option<String>,
+        page: Option<u32>,
+        since: Option<u64>,
+        until: Option<u64>,
+        #[query(required)]
+        r#window: bool,
+    }
+
+    fn join_tags(tags: &[String]) -> Result<String, std::convert::Infallible> {
+        Ok(tags.join(","))
+    }
+
+    #[derive(QueryParams, Debug)]
+    #[query(max_length = 64)]
+    struct TestItemMaxLength {
+        #[query(required, with_try = "join_tags")]
+        tags: Vec<String>,
+    }
+
+    static COUNTED_TAGS_CALLS: std::sync::atomic::AtomicUsize =
+        std::sync::atomic::AtomicUsize::new(0);
+
+    fn counted_tags(tags: &[String]) -> Result<String, std::convert::Infallible> {
+        COUNTED_TAGS_CALLS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        Ok(tags.join(" "))
+    }
+
+    #[derive(QueryParams, Debug)]
+    #[query(max_length = 80)]
+    struct TestItemMaxLengthEncodings {
+        #[query(required, with_try = "counted_tags", alias = "t")]
+        tags: Vec<String>,
+        #[query(raw)]
+        cursor: Option<String>,
+        #[query(rename = "form value", encode = "form")]
+        form: Option<String>,
+        #[query(encode = "component")]
+        path: Option<String>,
+    }
+
+    #[derive(QueryParams, Debug)]
+    struct TestItemUnit;
+
+    #[derive(QueryParams, Debug)]
+    struct TestItemEmptyTuple();
+
+    #[derive(QueryParams, Debug)]
+    struct TestItemEmptyBraces {}
+
+    #[derive(QueryParams, Debug)]
+    struct TestItemWhen {
+        #[query(exclude)]
+        signed: bool,
+        #[query(required)]
+        user: String,
+        #[query(required, when = "Self::is_signed")]
+        signature: String,
+        #[query(when = "Self::is_signed")]
+        nonce: Option<u64>,
+    }
+
+    impl TestItemWhen {
+        fn is_signed(&self) -> bool {
+            self.signed
+        }
+    }
+
+    #[derive(QueryParams, Debug)]
+    struct TestItemSensitive {
+        #[query(required, sensitive, rename = "apiKey")]
+        api_key: String,
+        #[query(sensitive)]
+        token: Option<String>,
+        #[query(required)]
+        term: String,
+        #[query(nested, sensitive)]
+        address: Option<TestItemAddress>,
+    }
+
+    #[derive(QueryParams, Debug)]
+    struct TestItemSensitiveBounded {
+        #[query(required, min = 1, sensitive)]
+        limit: u32,
+    }
+
+    #[derive(QueryParams, Debug)]
+    #[query(redact_with = "[REDACTED]")]
+    struct TestItemRedactWith {
+        #[query(required, sensitive)]
+        secret: String,
+        #[query(required, nested)]
+        inner: TestItemSensitive,
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[derive(QueryParams, Debug)]
+    struct TestItemJsonValue {
+        #[query(required, json)]
+        filter: serde_json::Value,
+        #[query(json)]
+        extra: Option<serde_json::Value>,
+        plain: Option<serde_json::Value>,
+    }
+
+    #[derive(QueryParams)]
+    struct TestItemInterleaved {
+        first: Option<String>,
+        #[query(required)]
+        second: u32,
+        third: Option<bool>,
+        #[query(required, rename = "fourth key")]
+        fourth: String,
+        fifth: Option<i64>,
+    }
+
+    #[derive(QueryParams)]
+    struct TestItemAlias {
+        #[query(required, rename = "pageSize", alias = "page_size", alias = "per page")]
+        page_size: u32,
+        #[query(alias = "q", sensitive)]
+        query: Option<String>,
+    }
+
+    #[derive(QueryParams)]
+    struct TestItemRepeatedAttributes {
+        #[query(required, required)]
+        #[query(required)]
+        a: i32,
+        #[query(skip_if = "is_negative", skip_if = "is_large", skip_if = "is_negative")]
+        b: Option<i32>,
+    }
+
+    fn is_large(value: &i32) -> bool {
+        *value > 100
+    }
+
+    #[derive(QueryParams)]
+    struct TestItemCow {
+        #[query(required, rename = "search term")]
+        term: String,
+        #[query(alias = "c")]
+        cursor: Option<String>,
+        #[query(required)]
+        page: u32,
+        #[query(required, transform = "lowercase")]
+        transformed: String,
+        #[query(none_pair("sort" = "relevance"))]
+        order: Option<String>,
+        #[query(default)]
+        fallback: Option<String>,
+        #[query(nested)]
+        address: Option<TestItemAddress>,
+    }
+
+    #[derive(QueryParams)]
+    #[query(rename_all = "camelCase")]
+    struct TestItemLineItem {
+        #[query(required)]
+        product_id: u32,
+        #[query(rename = "qty")]
+        quantity: Option<u32>,
+        #[query(nested)]
+        address: Option<TestItemAddress>,
+    }
+
+    #[derive(QueryParams)]
+    struct TestItemIndexed {
+        #[query(required)]
+        order: String,
+        #[query(required, indexed)]
+        items: Vec<TestItemLineItem>,
+        #[query(indexed, rename = "extra")]
+        extras: Option<Vec<TestItemAddress>>,
+    }
+
+    #[derive(QueryParams)]
+    struct TestItemFlatten {
+        #[query(required)]
+        page: u32,
+        #[query(flatten)]
+        extra: BTreeMap<String, String>,
+        #[query(flatten, sensitive)]
+        secrets: Option<HashMap<&'static str, u32>>,
+    }
+
+    #[derive(QueryParams)]
+    struct TestItemEncodeSchemes {
+        #[query(required)]
+        standard: String,
+        #[query(required, encode = "component")]
+        component: String,
+        #[query(required, rename = "form value", encode = "form")]
+        form: String,
+    }
+
+    #[derive(QueryParams, Debug)]
+    #[query(method = "as_query")]
+    struct TestItemMethod {
+        #[query(required)]
+        page: u32,
+        q: Option<String>,
+    }
+
+    #[derive(QueryParams, Debug)]
+    #[query(method = "r#type")]
+    struct TestItemRawMethod {
+        #[query(required)]
+        kind: String,
+    }
+
+    trait LegacyQuery {
+        fn to_query_params(&self) -> String;
+    }
+
+    impl LegacyQuery for TestItemMethod {
+        fn to_query_params(&self) -> String {
+            format!("legacy-{}", self.page)
+        }
+    }
+
+    #[cfg(feature = "utoipa")]
+    #[derive(QueryParams)]
+    #[query(utoipa, rename_all = "camelCase", skip_none = false)]
+    struct TestItemUtoipa {
+        #[query(required, alias = "per_page")]
+        page_size: u32,
+        #[query(rename = "q")]
+        search_term: Option<String>,
+        #[query(exclude)]
+        #[allow(dead_code)]
+        api_key: String,
+        #[query(nested)]
+        pagination: Option<TestItemOptionals>,
+        #[query(default)]
+        sort: Option<String>,
+        #[query(unwrap)]
+        session: Option<String>,
+        #[query(none_pair("mode" = "all"))]
+        filter: Option<String>,
+        #[query(required, skip_if = "is_negative")]
+        offset: i32,
+    }
+
+    // a type with its own `IntoParams` impl can still derive `QueryParams` without `utoipa`
+    #[cfg(feature = "utoipa")]
+    #[derive(QueryParams)]
+    struct TestItemOwnIntoParams {
+        #[query(required)]
+        page: u32,
+    }
+
+    #[cfg(feature = "utoipa")]
+    impl utoipa::IntoParams for TestItemOwnIntoParams {
+        fn into_params(
+            _: impl Fn() -> Option<utoipa::openapi::path::ParameterIn>,
+        ) -> Vec<utoipa::openapi::path::Parameter> {
+            Vec::new()
+        }
+    }
+
+    #[derive(QueryParams)]
+    struct TestItemRawChild {
+        #[query(required, raw)]
+        token: String,
+        #[query(rename = "form value", encode = "form")]
+        form: Option<String>,
+        #[query(required)]
+        plain: String,
+    }
+
+    #[derive(QueryParams)]
+    struct TestItemRawParent {
+        #[query(required, nested, rename = "the child")]
+        child: TestItemRawChild,
+        #[query(indexed)]
+        items: Option<Vec<TestItemRawChild>>,
+    }
+
+    mod without_trait_import {
+        use to_query_params::QueryParams;
+
+        #[derive(QueryParams)]
+        pub struct TestItemNoTraitImport {
+            #[query(required)]
+            pub a: i32,
+            pub b: Option<String>,
+        }
+    }
+
+    mod facade {
+        pub use to_query_params::*;
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    #[query(crate = "crate::tests::facade")]
+    struct TestItemCratePath {
+        #[query(required, rename = "a b")]
+        a: String,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemAddress {
+        #[query(required)]
+        city: String,
+        zip: Option<String>,
+    }
+
+    #[derive(QueryParams, Debug, PartialEq)]
+    struct TestItemNested {
+        #[query(required)]
+        name: String,
+        #[query(required, nested)]
+        address: TestItemAddress,
+        #[query(nested, rename = "billing")]
+        billing_address: Option<TestItemAddress>,
+    }
+
+    #[test]
+    fn test_developer_experience() {
+        let t = trybuild::TestCases::new();
+        t.compile_fail("tests/ui/*.rs");
+        #[cfg(feature = "regex")]
+        t.compile_fail("tests/ui-regex/*.rs");
+    }
+
+    #[test]
+    fn test_query_params_required_case() {
+        let test_item = TestItem { a: 0, b: 1 };
+
+        let expected = vec![
+            ("a".to_string(), "0".to_string()),
+            ("b".to_string(), "1".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_query_params_capped() {
+        let test_item = TestItem { a: 0, b: 1 };
+
+        let expected = vec![
+            ("a".to_string(), "0".to_string()),
+            ("b".to_string(), "1".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params_capped(2), Ok(expected));
+        assert_eq!(
+            test_item.to_query_params_capped(1),
+            Err(TooManyParams { count: 2, max: 1 })
+        );
+    }
+
+    #[test]
+    fn test_system_time_as_epoch() {
+        let test_item = TestItemSystemTime {
+            created: UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+            updated: Some(UNIX_EPOCH + Duration::from_millis(1_700_000_000_456)),
+        };
+
+        let expected = vec![
+            ("created".to_string(), "1700000000".to_string()),
+            ("updated".to_string(), "1700000000456".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_system_time_before_epoch_saturates() {
+        let test_item = TestItemSystemTime {
+            created: UNIX_EPOCH - Duration::from_secs(60),
+            updated: None,
+        };
+
+        let expected = vec![("created".to_string(), "0".to_string())];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_qualified_primitive_paths() {
+        let test_item = TestItemQualifiedPrimitives {
+            a: 1,
+            b: Some(2),
+            c: None,
+        };
+
+        let expected = vec![
+            ("a".to_string(), "1".to_string()),
+            ("b".to_string(), "2".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_skip_if_attributes_include() {
+        let test_item = TestItemSkipIf {
+            a: "a".to_string(),
+            b: Some(1),
+            c: Some("c".to_string()),
+        };
+
+        let expected = vec![
+            ("a".to_string(), "a".to_string()),
+            ("b".to_string(), "1".to_string()),
+            ("c".to_string(), "c".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_skip_if_attributes_skip() {
+        let test_item = TestItemSkipIf {
+            a: "".to_string(),
+            b: Some(-1),
+            c: Some("-".to_string()),
+        };
+
+        assert_eq!(test_item.to_query_params(), vec![]);
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_uuid_formats() {
+        let id = uuid::Uuid::from_u128(0x67e5504410b1426f9247bb680e5fe0c8);
+
+        let test_item = TestItemUuid {
+            default: id,
+            hyphenated: id,
+            simple: id,
+            urn: id,
+            optional: Some(id),
+        };
+
+        let expected = vec![
+            (
+                "default".to_string(),
+                "67e55044-10b1-426f-9247-bb680e5fe0c8".to_string(),
+            ),
+            (
+                "hyphenated".to_string(),
+                "67e55044-10b1-426f-9247-bb680e5fe0c8".to_string(),
+            ),
+            (
+                "simple".to_string(),
+                "67e5504410b1426f9247bb680e5fe0c8".to_string(),
+            ),
+            (
+                "urn".to_string(),
+                "urn:uuid:67e55044-10b1-426f-9247-bb680e5fe0c8".to_string(),
+            ),
+            (
+                "optional".to_string(),
+                "67e5504410b1426f9247bb680e5fe0c8".to_string(),
+            ),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_uuid_optional_none() {
+        let id = uuid::Uuid::nil();
+
+        let test_item = TestItemUuid {
+            default: id,
+            hyphenated: id,
+            simple: id,
+            urn: id,
+            optional: None,
+        };
+
+        let actual = test_item.to_query_params();
+
+        assert_eq!(actual.len(), 4);
+        assert!(actual.iter().all(|(key, _)| key != "optional"));
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64_attribute() {
+        let test_item = TestItemBase64 {
+            vec: vec![0xfb, 0xff, 0xfe],
+            slice: b"token",
+            array: [0, 1, 2, 3],
+            optional: Some(vec![0xff]),
+        };
+
+        let expected = vec![
+            ("vec".to_string(), "-__-".to_string()),
+            ("slice".to_string(), "dG9rZW4".to_string()),
+            ("array".to_string(), "AAECAw".to_string()),
+            ("optional".to_string(), "_w".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.to_encoded_params(), expected);
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64_attribute_optional_none() {
+        let test_item = TestItemBase64 {
+            vec: vec![],
+            slice: &[],
+            array: [0; 4],
+            optional: None,
+        };
+
+        let expected = vec![
+            ("vec".to_string(), "".to_string()),
+            ("slice".to_string(), "".to_string()),
+            ("array".to_string(), "AAAAAA".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_path_fields() {
+        let test_item = TestItemPaths {
+            required: PathBuf::from("/tmp/data.csv"),
+            borrowed: Path::new("relative/file name.txt"),
+            optional: Some(PathBuf::from("./config.toml")),
+        };
+
+        let expected = vec![
+            ("required".to_string(), "/tmp/data.csv".to_string()),
+            ("borrowed".to_string(), "relative/file name.txt".to_string()),
+            ("optional".to_string(), "./config.toml".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_path_fields_optional_none() {
+        let test_item = TestItemPaths {
+            required: PathBuf::from("a"),
+            borrowed: Path::new("b"),
+            optional: None,
+        };
+
+        let expected = vec![
+            ("required".to_string(), "a".to_string()),
+            ("borrowed".to_string(), "b".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_transform_pipeline() {
+        let test_item = TestItemTransforms {
+            a: "  Hello World ".to_string(),
+            b: Some("ABCDEF".to_string()),
+        };
+
+        let expected = vec![
+            ("a".to_string(), "hello world".to_string()),
+            ("b".to_string(), "abc".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn test_json_attribute() {
+        let test_item = TestItemJson {
+            filter: TestFilter {
+                status: vec!["open".to_string()],
+                min: 5,
+            },
+            tags: None,
+        };
+
+        let expected = vec![(
+            "filter".to_string(),
+            r#"{"status":["open"],"min":5}"#.to_string(),
+        )];
+
+        let expected_encoded = vec![(
+            "filter".to_string(),
+            "%7B%22status%22%3A%5B%22open%22%5D%2C%22min%22%3A5%7D".to_string(),
+        )];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.to_encoded_params(), expected_encoded);
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn test_json_attribute_optional() {
+        let test_item = TestItemJson {
+            filter: TestFilter {
+                status: vec![],
+                min: 0,
+            },
+            tags: Some(vec!["a".to_string(), "b".to_string()]),
+        };
+
+        let expected = vec![
+            ("filter".to_string(), r#"{"status":[],"min":0}"#.to_string()),
+            ("tags".to_string(), r#"["a","b"]"#.to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn test_value_object_attribute() {
+        let test_item = TestItemValueObject {
+            id: 1,
+            params: serde_json::json!({
+                "name": "a b",
+                "count": 5,
+                "active": true,
+                "missing": null,
+                "tags": ["x", "y"],
+                "range": {"min": 1},
+            }),
+            extra: Some(serde_json::json!({"page": 2})),
+        };
+
+        let expected = vec![
+            ("id".to_string(), "1".to_string()),
+            ("active".to_string(), "true".to_string()),
+            ("count".to_string(), "5".to_string()),
+            ("name".to_string(), "a b".to_string()),
+            ("range".to_string(), r#"{"min":1}"#.to_string()),
+            ("tags".to_string(), r#"["x","y"]"#.to_string()),
+            ("page".to_string(), "2".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(
+            test_item.to_encoded_params()[3],
+            ("name".to_string(), "a%20b".to_string())
+        );
+        assert_eq!(test_item.params_len(), expected.len());
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    #[should_panic(expected = "#[query(value_object)] field must be a JSON object")]
+    fn test_value_object_attribute_non_object() {
+        let test_item = TestItemValueObject {
+            id: 1,
+            params: serde_json::json!([1, 2]),
+            extra: None,
+        };
+
+        test_item.to_query_params();
+    }
+
+    #[test]
+    fn test_exclude_if_attribute() {
+        let mut test_item = TestItemExcludeIf {
+            debug: true,
+            trace_id: "abc".to_string(),
+            verbosity: Some(2),
+        };
+
+        let expected = vec![
+            ("trace_id".to_string(), "abc".to_string()),
+            ("verbosity".to_string(), "2".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+
+        test_item.debug = false;
+
+        assert_eq!(test_item.to_query_params(), vec![]);
+    }
+
+    #[test]
+    fn test_into_query_params() {
+        let test_item = TestItemOwned {
+            name: "Jo".to_string(),
+            count: 3,
+            note: Some("".to_string()),
+            email: Some("jo@example.com".to_string()),
+            label: None,
+        };
+
+        let expected = vec![
+            ("name".to_string(), "Jo".to_string()),
+            ("count".to_string(), "3".to_string()),
+            ("email".to_string(), "jo@example.com".to_string()),
+            ("tag".to_string(), "none".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.into_query_params(), expected);
+    }
+
+    #[test]
+    fn test_into_query_params_moves_strings() {
+        let name = "Jo".to_string();
+        let email = "jo@example.com".to_string();
+        let name_ptr = name.as_ptr();
+        let email_ptr = email.as_ptr();
+
+        let test_item = TestItemOwned {
+            name,
+            count: 3,
+            note: None,
+            email: Some(email),
+            label: None,
+        };
+
+        let query_params = test_item.into_query_params();
+
+        assert_eq!(query_params[0].1.as_ptr(), name_ptr);
+        assert_eq!(query_params[2].1.as_ptr(), email_ptr);
+    }
+
+    #[test]
+    fn test_into_query_params_exclude_if_reads_moved_field() {
+        let test_item = TestItemOwned {
+            name: "".to_string(),
+            count: 3,
+            note: Some("hi".to_string()),
+            email: Some("jo@example.com".to_string()),
+            label: Some("a".to_string()),
+        };
+
+        let expected = vec![
+            ("name".to_string(), "".to_string()),
+            ("count".to_string(), "3".to_string()),
+            ("note".to_string(), "hi".to_string()),
+            ("tag".to_string(), "a".to_string()),
+        ];
+
+        assert_eq!(test_item.clone().into_query_params(), expected);
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(
+            TestItemExcludeIf {
+                debug: true,
+                trace_id: "abc".to_string(),
+                verbosity: None,
+            }
+            .into_query_params(),
+            vec![("trace_id".to_string(), "abc".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_none_pair_attribute_some() {
+        let test_item = TestItemNonePair {
+            limit: Some(10),
+            sort: Some("asc".to_string()),
+        };
+
+        let expected = vec![
+            ("limit".to_string(), "10".to_string()),
+            ("sort order".to_string(), "asc".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_none_pair_attribute_none() {
+        let test_item = TestItemNonePair {
+            limit: None,
+            sort: None,
+        };
+
+        let expected = vec![
+            ("mode".to_string(), "default".to_string()),
+            ("sort order".to_string(), "a b".to_string()),
+        ];
+
+        let expected_encoded = vec![
+            ("mode".to_string(), "default".to_string()),
+            ("sort%20order".to_string(), "a%20b".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.to_encoded_params(), expected_encoded);
+    }
+
+    #[test]
+    fn test_sort_container_attribute() {
+        let test_item = TestItemSorted {
+            a: Some("a".to_string()),
+            c: 42,
+            b: Some(true),
+        };
+
+        let expected = vec![
+            ("b".to_string(), "true".to_string()),
+            ("c".to_string(), "42".to_string()),
+            ("zeta".to_string(), "a".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.to_encoded_params(), expected);
+    }
+
+    #[test]
+    fn test_sorted_container_attribute() {
+        let test_item = TestItemSortedDuplicates {
+            second_tag: "new".to_string(),
+            address: Some(TestItemAddress {
+                city: "Rome".to_string(),
+                zip: None,
+            }),
+            first_tag: Some("blue".to_string()),
+            z: 1,
+        };
+
+        let expected = vec![
+            ("address[city]".to_string(), "Rome".to_string()),
+            ("b".to_string(), "1".to_string()),
+            ("tag".to_string(), "blue".to_string()),
+            ("tag".to_string(), "new".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_raw_attribute() {
+        let test_item = TestItemRaw {
+            token: "a%2Fb%3D".to_string(),
+            cursor: Some("c%20d".to_string()),
+            encoded: "a/b=".to_string(),
+        };
+
+        let expected = vec![
+            ("token".to_string(), "a%2Fb%3D".to_string()),
+            ("encoded".to_string(), "a%2Fb%3D".to_string()),
+            ("next%5Bcursor%5D".to_string(), "c%20d".to_string()),
+        ];
+
+        assert_eq!(test_item.to_encoded_params(), expected);
+
+        let mut query_string = String::new();
+        test_item.write_query_string_into(&mut query_string);
+
+        assert_eq!(
+            query_string,
+            "token=a%2Fb%3D&encoded=a%2Fb%3D&next%5Bcursor%5D=c%20d"
+        );
+    }
+
+    #[test]
+    fn test_normalized_params() {
+        let test_item = TestItemRaw {
+            token: "a%2fb%3d".to_string(),
+            encoded: "a/b".to_string(),
+            cursor: Some("100%-%zz".to_string()),
+        };
+
+        let expected = vec![
+            ("token".to_string(), "a%2Fb%3D".to_string()),
+            ("encoded".to_string(), "a%2Fb".to_string()),
+            ("next%5Bcursor%5D".to_string(), "100%-%zz".to_string()),
+        ];
+
+        assert_eq!(test_item.to_normalized_params(), expected);
+    }
+
+    #[test]
+    fn test_raw_attribute_optional_none() {
+        let test_item = TestItemRaw {
+            token: "a%2Fb%3D".to_string(),
+            cursor: None,
+            encoded: "".to_string(),
+        };
+
+        let expected = vec![
+            ("token".to_string(), "a%2Fb%3D".to_string()),
+            ("encoded".to_string(), "".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.to_encoded_params(), expected);
+    }
+
+    #[test]
+    fn test_encode_modes() {
+        let test_item = TestItemEncodeModes {
+            value_only: "a b".to_string(),
+            key_only: "%20".to_string(),
+            both: "a b".to_string(),
+            none: Some("a%20b".to_string()),
+        };
+
+        let expected = vec![
+            ("items[]".to_string(), "a%20b".to_string()),
+            ("keys%5B%5D".to_string(), "%20".to_string()),
+            ("both%5B%5D".to_string(), "a%20b".to_string()),
+            ("none[]".to_string(), "a%20b".to_string()),
+        ];
+
+        assert_eq!(test_item.to_encoded_params(), expected);
+        assert_eq!(test_item.to_query_params()[0].0, "items[]");
+    }
+
+    #[test]
+    fn test_format_attribute() {
+        let test_item = TestItemFormat {
+            price: 9.987,
+            padded: 42,
+            hex: Some(255),
+            escaped: Some(1),
+        };
+
+        let expected = vec![
+            ("price".to_string(), "9.99".to_string()),
+            ("padded".to_string(), "0042".to_string()),
+            ("hex".to_string(), "0xff".to_string()),
+            ("escaped".to_string(), "{1}".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_nested_attribute() {
+        let test_item = TestItemNested {
+            name: "Jo".to_string(),
+            address: TestItemAddress {
+                city: "New York".to_string(),
+                zip: Some("10001".to_string()),
+            },
+            billing_address: Some(TestItemAddress {
+                city: "Boston".to_string(),
+                zip: None,
+            }),
+        };
+
+        let expected = vec![
+            ("name".to_string(), "Jo".to_string()),
+            ("address[city]".to_string(), "New York".to_string()),
+            ("address[zip]".to_string(), "10001".to_string()),
+            ("billing[city]".to_string(), "Boston".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+
+        let expected_encoded = vec![
+            ("name".to_string(), "Jo".to_string()),
+            ("address%5Bcity%5D".to_string(), "New%20York".to_string()),
+            ("address%5Bzip%5D".to_string(), "10001".to_string()),
+            ("billing%5Bcity%5D".to_string(), "Boston".to_string()),
+        ];
+
+        assert_eq!(test_item.to_encoded_params(), expected_encoded);
+        assert_eq!(TestItemNested::QUERY_KEYS, &["name"]);
+    }
+
+    #[test]
+    fn test_nested_attribute_optional_none() {
+        let test_item = TestItemNested {
+            name: "Jo".to_string(),
+            address: TestItemAddress {
+                city: "Paris".to_string(),
+                zip: None,
+            },
+            billing_address: None,
+        };
+
+        let expected = vec![
+            ("name".to_string(), "Jo".to_string()),
+            ("address[city]".to_string(), "Paris".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_param_names() {
+        assert_eq!(TestExcludeItem::param_names(), TestExcludeItem::QUERY_KEYS);
+        assert_eq!(
+            TestItemMixedRequiredOptionalsAndRename::param_names(),
+            &["alpha", "b", "gamma"]
+        );
+        assert_eq!(TestItemNested::param_names(), &["name"]);
+    }
+
+    #[test]
+    fn test_query_keys_constant() {
+        assert_eq!(TestExcludeItem::QUERY_KEYS, &["a", "d"]);
+        assert_eq!(
+            TestItemMixedRequiredOptionalsAndRename::QUERY_KEYS,
+            &["alpha", "b", "gamma"]
+        );
+        assert_eq!(
+            TestItemNonePair::QUERY_KEYS,
+            &["limit", "mode", "sort order"]
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono_fields() {
+        let created = chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let day = chrono::NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+
+        let test_item = TestItemChrono {
+            created,
+            day,
+            updated: Some(created.fixed_offset()),
+            due: Some(day),
+        };
+
+        let expected = vec![
+            (
+                "created".to_string(),
+                "2023-11-14T22:13:20+00:00".to_string(),
+            ),
+            ("day".to_string(), "2024-02-29".to_string()),
+            ("updated".to_string(), "2023-11-14".to_string()),
+            ("due".to_string(), "29/02/2024".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_own_datetime_type() {
+        let test_item = TestItemOwnDateTime {
+            created: calendar::DateTime(3),
+        };
+
+        assert_eq!(
+            test_item.to_query_params(),
+            vec![("created".to_string(), "day-3".to_string())]
+        );
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time_fields() {
+        let day = time::Date::from_calendar_date(2024, time::Month::February, 29).unwrap();
+        let created = day
+            .with_hms(22, 13, 20)
+            .unwrap()
+            .assume_offset(time::UtcOffset::from_hms(2, 0, 0).unwrap());
+
+        let test_item = TestItemTime {
+            created,
+            day,
+            updated: Some(created),
+            due: Some(day),
+        };
+
+        let expected = vec![
+            (
+                "created".to_string(),
+                "2024-02-29T22:13:20+02:00".to_string(),
+            ),
+            ("day".to_string(), "2024-02-29".to_string()),
+            ("updated".to_string(), "2024-02-29".to_string()),
+            ("due".to_string(), "29/02/2024".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.try_to_query_params().unwrap(), expected);
+
+        let test_item = TestItemTime {
+            created: created.replace_day(1).unwrap().replace_year(-1).unwrap(),
+            day,
+            updated: None,
+            due: None,
+        };
+
+        let error = test_item.try_to_query_params().unwrap_err();
+        assert_eq!(error.field(), Some("created"));
+    }
+
+    #[test]
+    fn test_allow_duplicate_keys() {
+        let test_item = TestItemDuplicateKeys {
+            first: 1,
+            second: Some(2),
+        };
+
+        let expected = vec![
+            ("id".to_string(), "1".to_string()),
+            ("id".to_string(), "2".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_exclude_attribute() {
+        let test_item = TestExcludeItem {
+            a: 0,
+            b: 1,
+            c: Some(2),
+            d: 3,
+        };
+
+        let expected = vec![
+            ("a".to_string(), "0".to_string()),
+            ("d".to_string(), "3".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_query_params_encoding() {
+        let test_item = TestStringItem {
+            a: "please encode me".into(),
+            b: "this works?".into(),
+        };
+
+        let expected = vec![
+            ("a".to_string(), "please%20encode%20me".to_string()),
+            ("please%20encode".to_string(), "this%20works%3F".to_string()),
+        ];
+
+        assert_eq!(test_item.to_encoded_params(), expected);
+    }
+
+    #[test]
+    fn test_query_params_encoded_once() {
+        let test_item = TestStringItem {
+            a: "a b".into(),
+            b: "100%".into(),
+        };
+
+        let expected = vec![
+            ("a".to_string(), "a b".to_string()),
+            ("please encode".to_string(), "100%".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+
+        let expected_encoded = vec![
+            ("a".to_string(), "a%20b".to_string()),
+            ("please%20encode".to_string(), "100%25".to_string()),
+        ];
+
+        assert_eq!(test_item.to_encoded_params(), expected_encoded);
+    }
+
+    #[test]
+    fn test_unencoded_container_attribute() {
+        let test_item = TestItemUnencoded {
+            sort: "a b".to_string(),
+            filter: Some("x&y".to_string()),
+        };
+
+        let expected = vec![
+            ("sort order".to_string(), "a b".to_string()),
+            ("filter".to_string(), "x&y".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.to_encoded_params(), expected);
+
+        let test_item = TestItemEncoded {
+            filter: "x&y".to_string(),
+        };
+
+        assert_eq!(
+            test_item.to_encoded_params(),
+            vec![("filter".to_string(), "x%26y".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_ip_addr_fields() {
+        let test_item = TestItemIpAddrs {
+            v4: Ipv4Addr::new(127, 0, 0, 1),
+            v6: Ipv6Addr::LOCALHOST,
+            bracketed_v6: "fe80::1".parse().unwrap(),
+            any: Some(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+        };
+
+        let expected = vec![
+            ("v4".to_string(), "127.0.0.1".to_string()),
+            ("v6".to_string(), "::1".to_string()),
+            ("bracketed".to_string(), "[fe80::1]".to_string()),
+            ("any".to_string(), "[::1]".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+
+        let expected_encoded = vec![
+            ("v4".to_string(), "127.0.0.1".to_string()),
+            ("v6".to_string(), "%3A%3A1".to_string()),
+            ("bracketed".to_string(), "%5Bfe80%3A%3A1%5D".to_string()),
+            ("any".to_string(), "%5B%3A%3A1%5D".to_string()),
+        ];
+
+        assert_eq!(test_item.to_encoded_params(), expected_encoded);
+    }
+
+    #[test]
+    fn test_bracket_ipv6_leaves_ipv4_as_is() {
+        let test_item = TestItemIpAddrs {
+            v4: Ipv4Addr::UNSPECIFIED,
+            v6: Ipv6Addr::UNSPECIFIED,
+            bracketed_v6: Ipv6Addr::UNSPECIFIED,
+            any: Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))),
+        };
+
+        assert_eq!(
+            test_item.to_query_params()[3],
+            ("any".to_string(), "10.0.0.1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_merge_query_params() {
+        let first = TestItem { a: 1, b: 2 };
+        let second = TestItemDuplicateKeys {
+            first: 3,
+            second: None,
+        };
+        let empty = TestItemOptionals { a: None, b: None };
+
+        let expected = vec![
+            ("a".to_string(), "1".to_string()),
+            ("b".to_string(), "2".to_string()),
+            ("id".to_string(), "3".to_string()),
+            ("a".to_string(), "1".to_string()),
+            ("b".to_string(), "2".to_string()),
+        ];
+
+        let sources: Vec<&dyn ToQueryParams> = vec![&first, &empty, &second, &first];
+
+        assert_eq!(merge_query_params(sources), expected);
+        assert_eq!(merge_query_params([]), vec![]);
+    }
+
+    #[test]
+    fn test_enum_values() {
+        let test_item = TestItemEnumValues {
+            order: TestSort::Relevance,
+            sort: Some(TestSort::Desc),
+        };
+
+        let expected = vec![
+            ("order".to_string(), "Relevance".to_string()),
+            ("sort".to_string(), "desc".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(TestSort::Asc.as_query_value(), "asc");
+        assert_eq!(TestSort::Asc.to_string(), "asc");
+    }
+
+    #[test]
+    fn test_order_attribute() {
+        let test_item = TestItemOrdered {
+            first_declared: Some(0),
+            timestamp: 123,
+            api_key: Some("key".to_string()),
+            last_declared: true,
+            signature: "sig".to_string(),
+        };
+
+        let expected = vec![
+            ("api_key".to_string(), "key".to_string()),
+            ("timestamp".to_string(), "123".to_string()),
+            ("signature".to_string(), "sig".to_string()),
+            ("first_declared".to_string(), "0".to_string()),
+            ("last_declared".to_string(), "true".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.to_encoded_params(), expected);
+    }
+
+    #[test]
+    fn test_order_attribute_optional_none() {
+        let test_item = TestItemOrdered {
+            first_declared: None,
+            timestamp: 123,
+            api_key: None,
+            last_declared: false,
+            signature: "sig".to_string(),
+        };
+
+        let expected = vec![
+            ("timestamp".to_string(), "123".to_string()),
+            ("signature".to_string(), "sig".to_string()),
+            ("last_declared".to_string(), "false".to_string()),
+        ];
+
+        assert_eq!(test_item.into_query_params(), expected);
+    }
+
+    #[test]
+    fn test_other_attributes_ignored() {
+        let test_item = TestItemOtherAttributes {
+            id: 1,
+            name: Some("a".to_string()),
+        };
+
+        let expected = vec![
+            ("id".to_string(), "1".to_string()),
+            ("name".to_string(), "a".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(TestItemOtherAttributes::QUERY_KEYS, &["id", "name"]);
+    }
+
+    #[test]
+    fn test_query_params_with_options() {
+        let test_item = TestItemMixedRequiredOptionalsAndRename {
+            a: Some("x y".to_string()),
+            b: Some(false),
+            c: 3,
+        };
+
+        assert_eq!(
+            test_item.to_query_params_with(&QueryOptions::default()),
+            test_item.to_query_params()
+        );
+
+        let opts = QueryOptions {
+            encode: true,
+            sort: true,
+            prefix: Some("f ".to_string()),
+        };
+
+        let expected = vec![
+            ("f%20alpha".to_string(), "x%20y".to_string()),
+            ("f%20b".to_string(), "false".to_string()),
+            ("f%20gamma".to_string(), "3".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params_with(&opts), expected);
+
+        let opts = QueryOptions {
+            prefix: Some("f ".to_string()),
+            ..QueryOptions::default()
+        };
+
+        assert_eq!(
+            test_item.to_query_params_with(&opts)[0],
+            ("f alpha".to_string(), "x y".to_string())
+        );
+    }
+
+    #[test]
+    fn test_query_string() {
+        assert_eq!(TestItemOptionals { a: None, b: None }.to_query_string(), "");
+
+        let test_item = TestItemOptionals {
+            a: Some("".to_string()),
+            b: None,
+        };
+
+        assert_eq!(test_item.to_query_string(), "a=");
+
+        let test_item = TestStringItem {
+            a: "a&b=c".to_string(),
+            b: "d e".to_string(),
+        };
+
+        assert_eq!(
+            test_item.to_query_string(),
+            "a=a%26b%3Dc&please%20encode=d%20e"
+        );
+    }
+
+    #[test]
+    fn test_duration_fields() {
+        let test_item = TestItemDurations {
+            timeout: Duration::from_millis(30_999),
+            ttl: Some(Duration::from_secs(2)),
+            delay: Some(Duration::from_secs(5)),
+        };
+
+        let expected = vec![
+            ("timeout".to_string(), "30".to_string()),
+            ("ttl".to_string(), "2000".to_string()),
+            ("delay".to_string(), "5".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_duration_fields_optional_none() {
+        let test_item = TestItemDurations {
+            timeout: Duration::ZERO,
+            ttl: None,
+            delay: None,
+        };
+
+        assert_eq!(
+            test_item.to_query_params(),
+            vec![("timeout".to_string(), "0".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_to_url() {
+        let test_item = TestItem { a: 1, b: 2 };
+        let base = "https://api.example.com/v1/products";
+
+        assert_eq!(
+            test_item.to_url(base),
+            "https://api.example.com/v1/products?a=1&b=2"
+        );
+        assert_eq!(
+            test_item.to_url("https://api.example.com/v1/products?page=3"),
+            "https://api.example.com/v1/products?page=3&a=1&b=2"
+        );
+        assert_eq!(
+            test_item.to_url("https://api.example.com/v1/products?"),
+            "https://api.example.com/v1/products?a=1&b=2"
+        );
+        assert_eq!(
+            test_item.to_url("https://api.example.com/v1/products#frag"),
+            "https://api.example.com/v1/products?a=1&b=2#frag"
+        );
+        assert_eq!(
+            test_item.to_url("https://api.example.com/v1/products?page=3#frag?x"),
+            "https://api.example.com/v1/products?page=3&a=1&b=2#frag?x"
+        );
+    }
+
+    #[test]
+    fn test_to_url_no_params() {
+        let test_item = TestItemOptionals { a: None, b: None };
+
+        assert_eq!(
+            test_item.to_url("https://api.example.com/v1/products?#frag"),
+            "https://api.example.com/v1/products?#frag"
+        );
+    }
+
+    #[test]
+    fn test_default_attribute() {
+        let test_item = TestItemDefaults {
+            page: None,
+            verbose: None,
+            query: None,
+            limit: None,
+        };
+
+        let expected = vec![
+            ("page".to_string(), "0".to_string()),
+            ("verbose".to_string(), "false".to_string()),
+            ("q".to_string(), "".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.into_query_params(), expected);
+    }
+
+    #[test]
+    fn test_default_attribute_some() {
+        let test_item = TestItemDefaults {
+            page: Some(2),
+            verbose: Some(true),
+            query: Some("shoes".to_string()),
+            limit: Some(10),
+        };
+
+        let expected = vec![
+            ("page".to_string(), "2".to_string()),
+            ("verbose".to_string(), "true".to_string()),
+            ("q".to_string(), "shoes".to_string()),
+            ("limit".to_string(), "10".to_string()),
+        ];
+
+        assert_eq!(test_item.clone().into_query_params(), expected);
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_extend_params() {
+        let mut out = vec![("z".to_string(), "first".to_string())];
+
+        TestItemSorted {
+            a: Some("a".to_string()),
+            c: 42,
+            b: None,
+        }
+        .extend_params(&mut out);
+        TestItem { a: 1, b: 2 }.extend_params(&mut out);
+        TestItemHandWritten.extend_params(&mut out);
+
+        let expected = vec![
+            ("z".to_string(), "first".to_string()),
+            ("c".to_string(), "42".to_string()),
+            ("zeta".to_string(), "a".to_string()),
+            ("a".to_string(), "1".to_string()),
+            ("b".to_string(), "2".to_string()),
+            ("manual".to_string(), "yes".to_string()),
+        ];
+
+        assert_eq!(out, expected);
+    }
+
+    #[cfg(feature = "hyper")]
+    #[test]
+    fn test_build_uri() {
+        let test_item = TestStringItem {
+            a: "x y".to_string(),
+            b: "z".to_string(),
+        };
+
+        let uri = test_item
+            .build_uri("https://api.example.com/v1/products")
+            .unwrap();
+
+        assert_eq!(uri.path(), "/v1/products");
+        assert_eq!(uri.query(), Some("a=x%20y&please%20encode=z"));
+
+        let uri = test_item
+            .build_uri("https://api.example.com/v1/products?page=2")
+            .unwrap();
+
+        assert_eq!(uri.query(), Some("page=2&a=x%20y&please%20encode=z"));
+        assert!(test_item.build_uri("https://exa mple.com").is_err());
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_apply_to_uri() {
+        let test_item = TestStringItem {
+            a: "x y".to_string(),
+            b: "z".to_string(),
+        };
+
+        let uri: http::Uri = "https://user@api.example.com:8443/v1/products"
+            .parse()
+            .unwrap();
+        let uri = test_item.apply_to_uri(uri).unwrap();
+
+        assert_eq!(
+            uri,
+            "https://user@api.example.com:8443/v1/products?a=x%20y&please%20encode=z"
+        );
+
+        let uri: http::Uri = "https://api.example.com/v1/products?page=2&sort=a%2Cb"
+            .parse()
+            .unwrap();
+        let uri = test_item.apply_to_uri(uri).unwrap();
+
+        assert_eq!(uri.scheme_str(), Some("https"));
+        assert_eq!(uri.authority().unwrap(), "api.example.com");
+        assert_eq!(uri.path(), "/v1/products");
+        assert_eq!(
+            uri.query(),
+            Some("page=2&sort=a%2Cb&a=x%20y&please%20encode=z")
+        );
+
+        let uri: http::Uri = "/v1/products".parse().unwrap();
+        let uri = test_item.apply_to_uri(uri).unwrap();
+
+        assert_eq!(uri, "/v1/products?a=x%20y&please%20encode=z");
+        assert_eq!(uri.scheme(), None);
+        assert_eq!(uri.authority(), None);
+
+        let uri: http::Uri = "/v1/products?".parse().unwrap();
+        assert_eq!(
+            test_item.apply_to_uri(uri).unwrap(),
+            "/v1/products?a=x%20y&please%20encode=z"
+        );
+
+        let uri: http::Uri = "/v1/products?page=2".parse().unwrap();
+        assert_eq!(
+            TestItemOptionals { a: None, b: None }
+                .apply_to_uri(uri)
+                .unwrap(),
+            "/v1/products?page=2"
+        );
+    }
+
+    #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn test_to_url_search_params() {
+        let test_item = TestStringItem {
+            a: "x&y=z".to_string(),
+            b: "café".to_string(),
+        };
+
+        assert_eq!(
+            String::from(test_item.to_url_search_params().to_string()),
+            "a=x%26y%3Dz&please+encode=caf%C3%A9"
+        );
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn test_append_to_url() {
+        let test_item = TestStringItem {
+            a: "x&y=z".to_string(),
+            b: "café ☕".to_string(),
+        };
+
+        let mut url = url::Url::parse("https://api.example.com/v1/products").unwrap();
+        test_item.append_to_url(&mut url);
+
+        assert_eq!(
+            url.as_str(),
+            "https://api.example.com/v1/products?a=x%26y%3Dz&please+encode=caf%C3%A9+%E2%98%95"
+        );
+
+        let pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        assert_eq!(pairs, test_item.to_query_params());
+
+        let mut url = url::Url::parse("https://api.example.com/v1/products?page=2#top").unwrap();
+        test_item.append_to_url(&mut url);
+
+        assert_eq!(
+            url.query(),
+            Some("page=2&a=x%26y%3Dz&please+encode=caf%C3%A9+%E2%98%95")
+        );
+        assert_eq!(url.fragment(), Some("top"));
+
+        let mut url = url::Url::parse("https://api.example.com/v1/products").unwrap();
+        TestItemOptionals { a: None, b: None }.append_to_url(&mut url);
+
+        assert_eq!(url.as_str(), "https://api.example.com/v1/products");
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_to_path_and_query() {
+        let test_item = TestStringItem {
+            a: "x y&z".to_string(),
+            b: "ü/?".to_string(),
+        };
+
+        let path_and_query = test_item.to_path_and_query("/items").unwrap();
+        assert_eq!(path_and_query.path(), "/items");
+        assert_eq!(
+            path_and_query.query(),
+            Some("a=x%20y%26z&please%20encode=%C3%BC%2F%3F")
+        );
+
+        let path_and_query = test_item.to_path_and_query("/items?existing=1").unwrap();
+        assert_eq!(
+            path_and_query,
+            "/items?existing=1&a=x%20y%26z&please%20encode=%C3%BC%2F%3F"
+        );
+
+        let empty = TestItemOptionals { a: None, b: None };
+        assert_eq!(empty.to_path_and_query("/items").unwrap(), "/items");
+        assert_eq!(empty.to_path_and_query("items").unwrap(), "/items");
+        assert_eq!(empty.to_path_and_query("").unwrap(), "/");
+
+        let test_item = TestItemOptionals {
+            a: Some("a".to_string()),
+            b: None,
+        };
+        assert_eq!(test_item.to_path_and_query("items").unwrap(), "/items?a=a");
+        assert!(test_item.to_path_and_query("/it ems").is_err());
+    }
+
+    #[test]
+    fn test_rename_all_camel_case() {
+        assert_eq!(
+            TestItemCamelCase::QUERY_KEYS,
+            &[
+                "id",
+                "userId",
+                "apiKey",
+                "httpUrl",
+                "sslEnabled",
+                "page2Size",
+                "X-Trace",
+            ]
+        );
+
+        let test_item = TestItemCamelCase {
+            id: None,
+            user_id: Some(7),
+            api_key: None,
+            http_url: None,
+            ssl_enabled: None,
+            page2_size: None,
+            trace_id: None,
+        };
+
+        assert_eq!(
+            test_item.to_query_params(),
+            vec![("userId".to_string(), "7".to_string())]
+        );
+        assert_eq!(TestItemScreamingKebabCase::QUERY_KEYS, &["MAX-PRICE"]);
+    }
+
+    #[test]
+    fn test_skip_none_false() {
+        let test_item = TestItemKeepNone {
+            id: 1,
+            query: None,
+            page: None,
+            sort: None,
+            address: None,
+        };
+
+        let expected = vec![
+            ("id".to_string(), "1".to_string()),
+            ("q".to_string(), "".to_string()),
+            ("page".to_string(), "0".to_string()),
+            ("sort".to_string(), "relevance".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.to_query_string(), "id=1&q=&page=0&sort=relevance");
+
+        let test_item = TestItemKeepNone {
+            id: 1,
+            query: Some("a".to_string()),
+            page: None,
+            sort: None,
+            address: None,
+        };
+
+        assert_eq!(
+            test_item.to_query_params()[1],
+            ("q".to_string(), "a".to_string())
+        );
+    }
+
+    #[test]
+    fn test_to_maps_last_duplicate_wins() {
+        let test_item = TestItemDuplicateKeys {
+            first: 1,
+            second: Some(2),
+        };
+
+        let hashmap = test_item.to_hashmap();
+
+        assert_eq!(hashmap.len(), 1);
+        assert_eq!(hashmap["id"], "2");
+
+        let test_item = TestItemMixedRequiredOptionalsAndRename {
+            a: Some("a".to_string()),
+            b: Some(true),
+            c: 3,
+        };
+
+        let keys: Vec<String> = test_item.to_btreemap().into_keys().collect();
+        assert_eq!(keys, vec!["alpha", "b", "gamma"]);
+
+        let test_item = TestItemOAuth1 {
+            b5: "".to_string(),
+            a3: "first".to_string(),
+            c_at: "".to_string(),
+            a2: "".to_string(),
+            oauth_consumer_key: "".to_string(),
+            oauth_token: "".to_string(),
+            oauth_signature_method: "".to_string(),
+            oauth_timestamp: "".to_string(),
+            oauth_nonce: "".to_string(),
+            c2: "".to_string(),
+            a3_second: "second".to_string(),
+        };
+
+        assert_eq!(test_item.to_btreemap()["a3"], "second");
+    }
+
+    #[cfg(feature = "indexmap")]
+    #[test]
+    fn test_to_indexmap() {
+        let test_item = TestItemOAuth1 {
+            b5: "b".to_string(),
+            a3: "first".to_string(),
+            c_at: "c".to_string(),
+            a2: "".to_string(),
+            oauth_consumer_key: "".to_string(),
+            oauth_token: "".to_string(),
+            oauth_signature_method: "".to_string(),
+            oauth_timestamp: "".to_string(),
+            oauth_nonce: "".to_string(),
+            c2: "".to_string(),
+            a3_second: "second".to_string(),
+        };
+
+        let indexmap = test_item.to_indexmap();
+        let keys: Vec<&str> = indexmap.keys().map(String::as_str).take(4).collect();
+
+        assert_eq!(keys, vec!["b5", "a3", "c@", "a2"]);
+        assert_eq!(indexmap["a3"], "second");
+        assert_eq!(indexmap.get_index_of("a3"), Some(1));
+        assert_eq!(indexmap.len(), 10);
+        assert!(TestItemOptionals { a: None, b: None }
+            .to_indexmap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_to_maps_empty() {
+        let test_item = TestItemOptionals { a: None, b: None };
+
+        assert!(test_item.to_hashmap().is_empty());
+        assert!(test_item.to_btreemap().is_empty());
+    }
+
+    #[test]
+    fn test_display_container_attribute() {
+        let test_item = TestItemDisplay {
+            name: "a b",
+            value: Some(1.5),
+        };
+
+        assert_eq!(test_item.to_string(), "name=a%20b&value=1.5");
+        assert_eq!(format!("{test_item}"), test_item.to_query_string());
+
+        let test_item: TestItemDisplay<i32> = TestItemDisplay {
+            name: "",
+            value: None,
+        };
+
+        assert_eq!(test_item.to_string(), "name=");
+    }
+
+    #[test]
+    fn test_into_iterator_container_attribute() {
+        let test_item = TestItemIntoIterator {
+            term: "red shoes",
+            page: Some(2),
+        };
+
+        let mut url = url::Url::parse("https://api.example.com/v1/products?a=1").unwrap();
+        url.query_pairs_mut().extend_pairs(&test_item);
+
+        assert_eq!(url.query(), Some("a=1&term=red+shoes&page=2"));
+
+        let pairs: Vec<(String, String)> = (&test_item).into_iter().collect();
+        assert_eq!(pairs, test_item.to_query_params());
+    }
+
+    #[test]
+    fn test_result_fields_ok() {
+        let test_item = TestItemResults {
+            id: 1,
+            count: Ok(5),
+            query: Ok("shoes".to_string()),
+            mode: Ok("fast".to_string()),
+        };
+
+        let expected = vec![
+            ("id".to_string(), "1".to_string()),
+            ("count".to_string(), "5".to_string()),
+            ("q".to_string(), "shoes".to_string()),
+            ("mode".to_string(), "fast".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.into_query_params(), expected);
+    }
+
+    #[test]
+    fn test_result_fields_err() {
+        let test_item = TestItemResults {
+            id: 1,
+            count: Err("failed".to_string()),
+            query: Err(()),
+            mode: Err(()),
+        };
+
+        let expected = vec![
+            ("id".to_string(), "1".to_string()),
+            ("mode".to_string(), "fallback".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_smart_pointer_fields() {
+        let test_item = TestItemSmartPointers {
+            name: Arc::new("shared".to_string()),
+            limit: Box::new(10),
+            ttl: Rc::new(Duration::from_secs(30)),
+            address: Some(Arc::new(TestItemAddress {
+                city: "Oslo".to_string(),
+                zip: None,
+            })),
+            region: Some(Arc::from("eu")),
+            tag: Some(Rc::new("new".to_string())),
+        };
+
+        let expected = vec![
+            ("name".to_string(), "shared".to_string()),
+            ("limit".to_string(), "10".to_string()),
+            ("ttl".to_string(), "30".to_string()),
+            ("address[city]".to_string(), "Oslo".to_string()),
+            ("region".to_string(), "eu".to_string()),
+            ("tag".to_string(), "new".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_smart_pointer_fields_optional_none() {
+        let test_item = TestItemSmartPointers {
+            name: Arc::new("shared".to_string()),
+            limit: Box::new(10),
+            ttl: Rc::new(Duration::ZERO),
+            address: None,
+            region: None,
+            tag: Some(Rc::new(String::new())),
+        };
+
+        assert_eq!(test_item.to_query_params().len(), 3);
+    }
+
+    #[test]
+    fn test_key_consts() {
+        assert_eq!(TestItemKeyConsts::KEY_MAX_PRICE, "maxPrice");
+        assert_eq!(TestItemKeyConsts::KEY_SEARCH_TERM, "q");
+
+        let test_item = TestItemKeyConsts {
+            max_price: 100,
+            search_term: Some("shoes".to_string()),
+            cache_key: "local".to_string(),
+            address: None,
+        };
+
+        let keys: Vec<String> = test_item
+            .to_query_params()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+
+        assert_eq!(
+            keys,
+            [
+                TestItemKeyConsts::KEY_MAX_PRICE,
+                TestItemKeyConsts::KEY_SEARCH_TERM
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unwrap_attribute() {
+        let test_item = TestItemUnwrap {
+            user_id: Some(42),
+            session: Some("abc".to_string()),
+            limit: None,
+        };
+
+        let expected = vec![
+            ("userId".to_string(), "42".to_string()),
+            ("session".to_string(), "abc".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.into_query_params(), expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "query field `user_id` was None, but is marked #[query(unwrap)]")]
+    fn test_unwrap_attribute_none_panics() {
+        let test_item = TestItemUnwrap {
+            user_id: None,
+            session: Some("abc".to_string()),
+            limit: Some(10),
+        };
+
+        test_item.to_query_params();
+    }
+
+    #[test]
+    fn test_write_query_string() {
+        let test_item = TestItemUnwrap {
+            user_id: Some(7),
+            session: Some("a b".to_string()),
+            limit: Some(10),
+        };
+
+        let mut url = String::from("https://example.com/?");
+        test_item.write_query_string(&mut url).unwrap();
+
+        assert_eq!(
+            url,
+            format!("https://example.com/?{}", test_item.to_query_string())
+        );
+    }
+
+    #[test]
+    fn test_write_query_string_streams_pairs() {
+        // records each write separately, so nothing is buffered into a `String` along the way
+        #[derive(Default)]
+        struct ChunkSink(Vec<String>);
+
+        impl fmt::Write for ChunkSink {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                self.0.push(s.to_string());
+                Ok(())
+            }
+        }
+
+        fn joined_encoded_params(item: &dyn ToQueryParams) -> String {
+            item.to_encoded_params()
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect::<Vec<String>>()
+                .join("&")
+        }
+
+        let items: Vec<Box<dyn ToQueryParams>> = vec![
+            Box::new(TestItemCow {
+                term: "red shoes".to_string(),
+                cursor: Some("a&b".to_string()),
+                page: 2,
+                transformed: "T".to_string(),
+                order: None,
+                fallback: None,
+                address: Some(TestItemAddress {
+                    city: "São Paulo".to_string(),
+                    zip: None,
+                }),
+            }),
+            Box::new(TestItemEncodeModes {
+                value_only: "a b".to_string(),
+                key_only: "%20".to_string(),
+                both: "x/y".to_string(),
+                none: Some("c d".to_string()),
+            }),
+            Box::new(TestItemEncodeSchemes {
+                standard: "a+b c/d".to_string(),
+                component: "a+b c/d".to_string(),
+                form: "a+b c/d*".to_string(),
+            }),
+            Box::new(TestItemNonePair {
+                limit: None,
+                sort: None,
+            }),
+            Box::new(TestItemOptionals { a: None, b: None }),
+        ];
+
+        for item in items {
+            let mut sink = ChunkSink::default();
+            item.write_query_string(&mut sink).unwrap();
+
+            let streamed = sink.0.concat();
+            assert_eq!(streamed, joined_encoded_params(item.as_ref()));
+            assert_eq!(streamed, item.to_query_string());
+        }
+    }
+
+    #[test]
+    fn test_write_query_string_propagates_errors() {
+        struct FailingWriter;
+
+        impl fmt::Write for FailingWriter {
+            fn write_str(&mut self, _: &str) -> fmt::Result {
+                Err(fmt::Error)
+            }
+        }
+
+        let test_item = TestItemUnwrap {
+            user_id: Some(7),
+            session: Some("abc".to_string()),
+            limit: None,
+        };
+
+        assert!(test_item.write_query_string(&mut FailingWriter).is_err());
+    }
+
+    #[test]
+    fn test_params_len_empty() {
+        let test_item = TestItemOptionals { a: None, b: None };
+
+        assert_eq!(test_item.params_len(), 0);
+        assert!(!test_item.has_params());
+    }
+
+    #[test]
+    fn test_params_len_all_required() {
+        let test_item = TestItem { a: 1, b: 2 };
+
+        assert_eq!(test_item.params_len(), 2);
+        assert!(test_item.has_params());
+        assert_eq!(TestItemHandWritten.params_len(), 1);
+    }
+
+    #[test]
+    fn test_params_len_mixed() {
+        let test_item = TestItemMixedRequiredOptionals {
+            a: Some("a".to_string()),
+            b: None,
+            c: 3,
+        };
+        assert_eq!(test_item.params_len(), 2);
+
+        let test_item = TestItemSkipIf {
+            a: String::new(),
+            b: Some(-1),
+            c: Some("c".to_string()),
+        };
+        assert_eq!(test_item.params_len(), test_item.to_query_params().len());
+
+        let test_item = TestItemNonePair {
+            limit: None,
+            sort: Some("asc".to_string()),
+        };
+        assert_eq!(test_item.params_len(), 2);
+
+        let test_item = TestItemNested {
+            name: "Jo".to_string(),
+            address: TestItemAddress {
+                city: "Paris".to_string(),
+                zip: Some("75001".to_string()),
+            },
+            billing_address: None,
+        };
+        assert_eq!(test_item.params_len(), 3);
+        assert_eq!(test_item.params_len(), test_item.to_query_params().len());
+    }
+
+    #[test]
+    fn test_try_to_query_params() {
+        let test_item = TestItemWithTry {
+            page_size: 50,
+            address: Some(TestItemAddress {
+                city: "Oslo".to_string(),
+                zip: None,
+            }),
+        };
+
+        let expected = vec![
+            ("pageSize".to_string(), "50".to_string()),
+            ("address[city]".to_string(), "Oslo".to_string()),
+        ];
+
+        assert_eq!(test_item.try_to_query_params().unwrap(), expected);
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_try_to_query_params_error() {
+        let test_item = TestItemWithTry {
+            page_size: 500,
+            address: None,
+        };
+
+        let error = test_item.try_to_query_params().unwrap_err();
+
+        assert_eq!(error.field(), Some("page_size"));
+        assert_eq!(
+            error.to_string(),
+            "query field `page_size` failed to serialize: 500 is over the limit of 100"
+        );
+        assert_eq!(
+            error.source().unwrap().to_string(),
+            "500 is over the limit of 100"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "query field `page_size` failed to serialize")]
+    fn test_with_try_panics_when_infallible() {
+        let test_item = TestItemWithTry {
+            page_size: 500,
+            address: None,
+        };
+
+        test_item.to_query_params();
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn test_try_to_query_params_json_error() {
+        let test_item = TestItemJsonFallible {
+            counts: Some(HashMap::from([(vec![1], 1)])),
+        };
+
+        let error = test_item.try_to_query_params().unwrap_err();
+        assert_eq!(error.field(), Some("counts"));
+
+        let test_item = TestItemJsonFallible { counts: None };
+        assert_eq!(test_item.try_to_query_params().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_raw_identifier_fields() {
+        let test_item = TestItemRawIdents {
+            r#type: "book".to_string(),
+            r#match: Some("exact".to_string()),
+            r#ref: Some(3),
+        };
+
+        let expected = vec![
+            ("type".to_string(), "book".to_string()),
+            ("matchMode".to_string(), "exact".to_string()),
+            ("ref".to_string(), "3".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.into_query_params(), expected);
+        assert_eq!(TestItemRawIdents::QUERY_KEYS, &["type", "matchMode", "ref"]);
+    }
+
+    #[test]
+    fn test_validation_passes() {
+        let test_item = TestItemValidated {
+            limit: 100,
+            term: Some("shoes".to_string()),
+            offset: Some(-1.5),
+        };
+
+        let expected = vec![
+            ("limit".to_string(), "100".to_string()),
+            ("term".to_string(), "shoes".to_string()),
+            ("offset".to_string(), "-1.5".to_string()),
+        ];
+
+        assert_eq!(test_item.try_to_query_params().unwrap(), expected);
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_validation_skips_none() {
+        let test_item = TestItemValidated {
+            limit: 1,
+            term: None,
+            offset: None,
+        };
+
+        assert_eq!(
+            test_item.try_to_query_params().unwrap(),
+            vec![("limit".to_string(), "1".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_validation_failures() {
+        let invalid = |limit, term: &str, offset| {
+            TestItemValidated {
+                limit,
+                term: Some(term.to_string()),
+                offset: Some(offset),
+            }
+            .try_to_query_params()
+            .unwrap_err()
+            .to_string()
+        };
+
+        assert_eq!(
+            invalid(0, "a", 0.0),
+            "query field `limit` is invalid: must be at least 1"
+        );
+        assert_eq!(
+            invalid(101, "a", 0.0),
+            "query field `limit` is invalid: must be at most 100"
+        );
+        assert_eq!(
+            invalid(10, "", 0.0),
+            "query field `term` is invalid: must not be empty"
+        );
+        assert_eq!(
+            invalid(10, "a", -2.0),
+            "query field `offset` is invalid: must be at least -1.5"
+        );
+    }
+
+    #[test]
+    fn test_validation_error_variant() {
+        let test_item = TestItemValidated {
+            limit: 0,
+            term: None,
+            offset: None,
+        };
+
+        match test_item.try_to_query_params() {
+            Err(QueryParamsError::Invalid { field, reason }) => {
+                assert_eq!(field, "limit");
+                assert_eq!(reason, "must be at least 1");
+            }
+            other => panic!("expected an invalid field error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "query field `limit` is invalid: must be at most 100")]
+    fn test_validation_panics_when_infallible() {
+        let test_item = TestItemValidated {
+            limit: 1000,
+            term: None,
+            offset: None,
+        };
+
+        test_item.to_query_params();
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_pattern_validation() {
+        let test_item = TestItemPattern {
+            slug: "red-shoes".to_string(),
+        };
+        assert_eq!(
+            test_item.try_to_query_params().unwrap(),
+            vec![("slug".to_string(), "red-shoes".to_string())]
+        );
+
+        let test_item = TestItemPattern {
+            slug: "Red Shoes".to_string(),
+        };
+        assert_eq!(
+            test_item.try_to_query_params().unwrap_err().to_string(),
+            "query field `slug` is invalid: must match the pattern `^[a-z-]+$`"
+        );
+    }
+
+    #[test]
+    fn test_bool_format_attribute() {
+        let test_item = TestItemBoolFormats {
+            numeric: true,
+            answer: Some(true),
+            switch: Some(Box::new(true)),
+            plain: Some(true),
+        };
+
+        let expected = vec![
+            ("numeric".to_string(), "1".to_string()),
+            ("answer".to_string(), "yes".to_string()),
+            ("switch".to_string(), "on".to_string()),
+            ("plain".to_string(), "true".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_bool_format_attribute_false() {
+        let test_item = TestItemBoolFormats {
+            numeric: false,
+            answer: Some(false),
+            switch: Some(Box::new(false)),
+            plain: None,
+        };
+
+        let expected = vec![
+            ("numeric".to_string(), "0".to_string()),
+            ("answer".to_string(), "no".to_string()),
+            ("switch".to_string(), "off".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_to_query_params_excluding() {
+        let test_item = TestItemNested {
+            name: "Jo".to_string(),
+            address: TestItemAddress {
+                city: "Paris".to_string(),
+                zip: Some("75001".to_string()),
+            },
+            billing_address: None,
+        };
+
+        let expected = vec![("address[zip]".to_string(), "75001".to_string())];
+
+        assert_eq!(
+            test_item.to_query_params_excluding(&["name", "address[city]", "missing"]),
+            expected
+        );
+        assert_eq!(
+            test_item.to_query_params_excluding(&[]),
+            test_item.to_query_params()
+        );
+    }
+
+    #[test]
+    fn test_exclusive_fields() {
+        let mut test_item = TestItemExclusive {
+            cursor: None,
+            page: None,
+            since: None,
+            until: None,
+            r#window: true,
+        };
+
+        assert_eq!(
+            test_item.try_to_query_params().unwrap(),
+            vec![("window".to_string(), "true".to_string())]
+        );
+
+        test_item.cursor = Some("abc".to_string());
+        assert_eq!(test_item.try_to_query_params().unwrap().len(), 2);
+
+        test_item.page = Some(2);
+        let error = test_item.try_to_query_params().unwrap_err();
+        assert_eq!(error.field(), Some("cursor"));
+        assert_eq!(
+            error.to_string(),
+            "query fields `cursor`, `page` can't be set together"
+        );
+    }
+
+    #[test]
+    fn test_exclusive_fields_three_way() {
+        let test_item = TestItemExclusive {
+            cursor: None,
+            page: None,
+            since: Some(1),
+            until: Some(2),
+            r#window: true,
+        };
+
+        match test_item.try_to_query_params() {
+            Err(QueryParamsError::Exclusive { fields }) => {
+                assert_eq!(fields, vec!["since", "until", "window"]);
+            }
+            other => panic!("expected an exclusive fields error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_exclusive_fields_only_checked_when_fallible() {
+        let test_item = TestItemExclusive {
+            cursor: Some("abc".to_string()),
+            page: Some(2),
+            since: None,
+            until: None,
+            r#window: false,
+        };
+
+        let expected = vec![
+            ("cursor".to_string(), "abc".to_string()),
+            ("page".to_string(), "2".to_string()),
+            ("window".to_string(), "false".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(
+            test_item.to_query_string(),
+            "cursor=abc&page=2&window=false"
+        );
+        assert!(test_item.try_to_query_params().is_err());
+    }
+
+    #[test]
+    fn test_try_to_query_string() {
+        let test_item = TestItemMaxLength {
+            tags: vec!["a b".to_string(), "c".to_string()],
+        };
+
+        assert_eq!(test_item.try_to_query_string(14).unwrap(), "tags=a%20b%2Cc");
+        assert_eq!(test_item.max_query_length(), Some(64));
+        assert_eq!(TestItem { a: 1, b: 2 }.max_query_length(), None);
+
+        match test_item.try_to_query_string(13) {
+            Err(QueryParamsError::TooLong { length, max }) => {
+                assert_eq!(length, 14);
+                assert_eq!(max, 13);
+            }
+            other => panic!("expected a too long error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_try_to_query_string_default() {
+        let mut test_item = TestItemMaxLength {
+            tags: vec!["tag".to_string(); 10],
+        };
+
+        assert_eq!(
+            test_item.try_to_query_string_default().unwrap(),
+            test_item.to_query_string()
+        );
+        assert_eq!(
+            TestItem { a: 1, b: 2 }
+                .try_to_query_string_default()
+                .unwrap(),
+            "a=1&b=2"
+        );
+
+        test_item.tags = vec!["tag".to_string(); 100];
+        match test_item.try_to_query_string_default() {
+            Err(QueryParamsError::TooLong { length, max }) => {
+                assert_eq!(length, 602);
+                assert_eq!(max, 64);
+            }
+            other => panic!("expected a too long error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_max_length_attribute() {
+        let mut test_item = TestItemMaxLength {
+            tags: vec!["tag".to_string(); 10],
+        };
+
+        assert_eq!(test_item.try_to_query_params().unwrap().len(), 1);
+
+        test_item.tags = vec!["tag".to_string(); 100];
+        let error = test_item.try_to_query_params().unwrap_err();
+
+        assert_eq!(error.field(), None);
+        assert_eq!(
+            error.to_string(),
+            "query string is 602 bytes long, but at most 64 are allowed"
+        );
+        assert_eq!(test_item.to_query_params().len(), 1);
+    }
+
+    #[test]
+    fn test_max_length_measures_encoded_pairs() {
+        use std::sync::atomic::Ordering;
+
+        let mut test_item = TestItemMaxLengthEncodings {
+            tags: vec!["a&b".to_string(), "c".to_string()],
+            cursor: Some("x y".to_string()),
+            form: Some("1 + 1".to_string()),
+            path: Some("/a b".to_string()),
+        };
+
+        COUNTED_TAGS_CALLS.store(0, Ordering::SeqCst);
+        assert_eq!(
+            test_item.try_to_query_params().unwrap(),
+            test_item.to_query_params()
+        );
+        assert_eq!(COUNTED_TAGS_CALLS.load(Ordering::SeqCst), 2);
+
+        test_item.tags = vec!["a&b".to_string(); 10];
+        COUNTED_TAGS_CALLS.store(0, Ordering::SeqCst);
+        let result = test_item.try_to_query_params();
+
+        // the value is formatted once, and the query string isn't built again for its length
+        assert_eq!(COUNTED_TAGS_CALLS.load(Ordering::SeqCst), 1);
+
+        match result {
+            Err(QueryParamsError::TooLong { length, max }) => {
+                assert_eq!(length, test_item.to_query_string().len());
+                assert_eq!(max, 80);
+            }
+            other => panic!("expected a TooLong error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_unit_and_empty_structs() {
+        assert_eq!(TestItemUnit.to_query_params(), vec![]);
+        assert_eq!(TestItemUnit.to_encoded_params(), vec![]);
+        assert_eq!(TestItemUnit.to_query_string(), "");
+        assert_eq!(TestItemUnit.try_to_query_params().unwrap(), vec![]);
+        assert!(!TestItemUnit.has_params());
+        assert!(TestItemUnit::QUERY_KEYS.is_empty());
+
+        assert_eq!(TestItemEmptyTuple().to_query_params(), vec![]);
+        assert_eq!(TestItemEmptyBraces {}.to_query_params(), vec![]);
+    }
+
+    #[test]
+    fn test_when_attribute() {
+        let test_item = TestItemWhen {
+            signed: true,
+            user: "jo".to_string(),
+            signature: "abc".to_string(),
+            nonce: Some(7),
+        };
+
+        let expected = vec![
+            ("user".to_string(), "jo".to_string()),
+            ("signature".to_string(), "abc".to_string()),
+            ("nonce".to_string(), "7".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.into_query_params(), expected);
+    }
+
+    #[test]
+    fn test_when_attribute_condition_false() {
+        let test_item = TestItemWhen {
+            signed: false,
+            user: "jo".to_string(),
+            signature: "abc".to_string(),
+            nonce: Some(7),
+        };
+
+        let expected = vec![("user".to_string(), "jo".to_string())];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.params_len(), 1);
+        assert_eq!(test_item.into_query_params(), expected);
+    }
+
+    #[test]
+    fn test_redacted_params() {
+        let test_item = TestItemSensitive {
+            api_key: "secret-key".to_string(),
+            token: Some("secret-token".to_string()),
+            term: "shoes".to_string(),
+            address: Some(TestItemAddress {
+                city: "Oslo".to_string(),
+                zip: None,
+            }),
+        };
+
+        let redacted = vec![
+            ("apiKey".to_string(), "***".to_string()),
+            ("token".to_string(), "***".to_string()),
+            ("term".to_string(), "shoes".to_string()),
+            ("address[city]".to_string(), "***".to_string()),
+        ];
+
+        let expected = vec![
+            ("apiKey".to_string(), "secret-key".to_string()),
+            ("token".to_string(), "secret-token".to_string()),
+            ("term".to_string(), "shoes".to_string()),
+            ("address[city]".to_string(), "Oslo".to_string()),
+        ];
+
+        assert_eq!(test_item.to_redacted_params(), redacted);
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[test]
+    fn test_redacted_params_optional_none() {
+        let test_item = TestItemSensitive {
+            api_key: "secret-key".to_string(),
+            token: None,
+            term: "shoes".to_string(),
+            address: None,
+        };
+
+        let redacted = vec![
+            ("apiKey".to_string(), "***".to_string()),
+            ("term".to_string(), "shoes".to_string()),
+        ];
+
+        assert_eq!(test_item.to_redacted_params(), redacted);
+    }
+
+    #[test]
+    fn test_redacted_params_skip_validation() {
+        let test_item = TestItemSensitiveBounded { limit: 0 };
+
+        let redacted = vec![("limit".to_string(), "***".to_string())];
+
+        assert_eq!(test_item.to_redacted_params(), redacted);
+        assert!(matches!(
+            test_item.try_to_query_params(),
+            Err(QueryParamsError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn test_redact_with_attribute() {
+        let test_item = TestItemRedactWith {
+            secret: "hunter2".to_string(),
+            inner: TestItemSensitive {
+                api_key: "secret-key".to_string(),
+                token: None,
+                term: "shoes".to_string(),
+                address: None,
+            },
+        };
+
+        let redacted = vec![
+            ("secret".to_string(), "[REDACTED]".to_string()),
+            ("inner[apiKey]".to_string(), "***".to_string()),
+            ("inner[term]".to_string(), "shoes".to_string()),
+        ];
+
+        assert_eq!(test_item.to_redacted_params(), redacted);
+        assert_eq!(TestItem { a: 1, b: 2 }.to_redacted_params().len(), 2);
+    }
+
+    #[test]
+    fn test_masked_params() {
+        let test_item = TestItemMixedRequiredOptionalsAndRename {
+            a: Some("a".to_string()),
+            b: Some(true),
+            c: 3,
+        };
+
+        assert_eq!(
+            test_item.to_masked_params(&["missing", "Alpha"]),
+            test_item.to_query_params()
+        );
+
+        let expected = vec![
+            ("alpha".to_string(), "***".to_string()),
+            ("b".to_string(), "true".to_string()),
+            ("gamma".to_string(), "***".to_string()),
+        ];
+
+        assert_eq!(test_item.to_masked_params(&["alpha", "gamma"]), expected);
+    }
+
+    #[test]
+    fn test_masked_params_duplicate_keys() {
+        let test_item = TestItemDuplicateKeys {
+            first: 1,
+            second: Some(2),
+        };
+
+        let expected = vec![
+            ("id".to_string(), "***".to_string()),
+            ("id".to_string(), "***".to_string()),
+        ];
+
+        assert_eq!(test_item.to_masked_params(&["id"]), expected);
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn test_json_value_fields() {
+        let filter = serde_json::json!({"status": ["open"], "name": "a b"});
+        let test_item = TestItemJsonValue {
+            filter: filter.clone(),
+            extra: Some(serde_json::json!("text")),
+            plain: Some(filter),
+        };
+
+        let expected = vec![
+            (
+                "filter".to_string(),
+                r#"{"name":"a b","status":["open"]}"#.to_string(),
+            ),
+            ("extra".to_string(), r#""text""#.to_string()),
+            (
+                "plain".to_string(),
+                r#"{"name":"a b","status":["open"]}"#.to_string(),
+            ),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.try_to_query_params().unwrap(), expected);
+    }
+
+    fn test_duplicates() -> QueryParamsBuilder {
+        let mut builder = QueryParamsBuilder::new();
+        builder
+            .push("a", 1)
+            .push("b", 2)
+            .push("a", 3)
+            .push("c", 4)
+            .push("a", 5);
+        builder
+    }
+
+    #[test]
+    fn test_unique_params_keep_first() {
+        let expected = vec![
+            ("a".to_string(), "1".to_string()),
+            ("b".to_string(), "2".to_string()),
+            ("c".to_string(), "4".to_string()),
+        ];
+
+        assert_eq!(
+            test_duplicates().to_unique_params(DuplicateStrategy::KeepFirst),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_unique_params_keep_last() {
+        let expected = vec![
+            ("b".to_string(), "2".to_string()),
+            ("c".to_string(), "4".to_string()),
+            ("a".to_string(), "5".to_string()),
+        ];
+
+        assert_eq!(
+            test_duplicates().to_unique_params(DuplicateStrategy::KeepLast),
+            expected
+        );
+    }
 
-    /// Creates a `Vec<(String, String)>` as the url-encoded (key, value) pairs for query parameters.
-    fn to_encoded_params(&self) -> Vec<(String, String)>;
-}
+    #[test]
+    fn test_unique_params_join_with() {
+        let expected = vec![
+            ("a".to_string(), "1,3,5".to_string()),
+            ("b".to_string(), "2".to_string()),
+            ("c".to_string(), "4".to_string()),
+        ];
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        assert_eq!(
+            test_duplicates().to_unique_params(DuplicateStrategy::JoinWith(',')),
+            expected
+        );
+    }
 
-    #[derive(QueryParams, Debug, PartialEq)]
-    struct TestItem {
-        #[query(required)]
-        a: i32,
-        #[query(required)]
-        b: i32,
+    #[test]
+    fn test_unique_params_already_unique() {
+        let test_item = TestItemMixedRequiredOptionals {
+            a: Some("a".to_string()),
+            b: Some(false),
+            c: 1,
+        };
+
+        for strategy in [
+            DuplicateStrategy::KeepFirst,
+            DuplicateStrategy::KeepLast,
+            DuplicateStrategy::JoinWith('|'),
+        ] {
+            assert_eq!(
+                test_item.to_unique_params(strategy),
+                test_item.to_query_params()
+            );
+        }
     }
 
-    #[derive(QueryParams, Debug, PartialEq)]
-    struct TestExcludeItem {
-        #[query(required)]
-        a: i32,
-        #[query(exclude)]
-        b: i32,
-        #[query(exclude)]
-        c: Option<i32>,
-        #[query(required)]
-        d: i32,
+    #[test]
+    fn test_declaration_order_interleaved() {
+        let test_item = TestItemInterleaved {
+            first: Some("a b".to_string()),
+            second: 2,
+            third: None,
+            fourth: "d".to_string(),
+            fifth: Some(5),
+        };
+
+        let expected = vec![
+            ("first".to_string(), "a b".to_string()),
+            ("second".to_string(), "2".to_string()),
+            ("fourth key".to_string(), "d".to_string()),
+            ("fifth".to_string(), "5".to_string()),
+        ];
+
+        let expected_encoded = vec![
+            ("first".to_string(), "a%20b".to_string()),
+            ("second".to_string(), "2".to_string()),
+            ("fourth%20key".to_string(), "d".to_string()),
+            ("fifth".to_string(), "5".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.try_to_query_params().unwrap(), expected);
+        assert_eq!(test_item.to_redacted_params(), expected);
+        assert_eq!(test_item.to_encoded_params(), expected_encoded);
+        assert_eq!(
+            TestItemInterleaved::QUERY_KEYS,
+            &["first", "second", "third", "fourth key", "fifth"]
+        );
+        assert_eq!(test_item.into_query_params(), expected);
     }
 
-    #[derive(QueryParams, Debug, PartialEq)]
-    struct TestStringItem {
-        #[query(required)]
-        a: String,
-        #[query(required, rename = "please encode")]
-        b: String,
+    #[test]
+    fn test_alias() {
+        let test_item = TestItemAlias {
+            page_size: 50,
+            query: Some("a b".to_string()),
+        };
+
+        let expected = vec![
+            ("pageSize".to_string(), "50".to_string()),
+            ("page_size".to_string(), "50".to_string()),
+            ("per page".to_string(), "50".to_string()),
+            ("query".to_string(), "a b".to_string()),
+            ("q".to_string(), "a b".to_string()),
+        ];
+
+        let expected_encoded = vec![
+            ("pageSize".to_string(), "50".to_string()),
+            ("page_size".to_string(), "50".to_string()),
+            ("per%20page".to_string(), "50".to_string()),
+            ("query".to_string(), "a%20b".to_string()),
+            ("q".to_string(), "a%20b".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.try_to_query_params().unwrap(), expected);
+        assert_eq!(test_item.to_encoded_params(), expected_encoded);
+        assert_eq!(test_item.params_len(), 5);
+        assert_eq!(
+            test_item.to_redacted_params()[3..],
+            [
+                ("query".to_string(), "***".to_string()),
+                ("q".to_string(), "***".to_string()),
+            ]
+        );
+        assert_eq!(
+            TestItemAlias::QUERY_KEYS,
+            &["pageSize", "page_size", "per page", "query", "q"]
+        );
+
+        let test_item = TestItemAlias {
+            page_size: 10,
+            query: None,
+        };
+        assert_eq!(test_item.params_len(), 3);
+        assert_eq!(
+            test_item.to_query_string(),
+            "pageSize=10&page_size=10&per%20page=10"
+        );
     }
 
-    #[derive(QueryParams, Debug, PartialEq)]
-    struct TestItemRequiredRename {
-        #[query(required, rename = "alpha")]
-        a: i32,
-        #[query(required)]
-        b: i32,
+    #[test]
+    fn test_repeated_attributes() {
+        let test_item = TestItemRepeatedAttributes { a: 1, b: Some(2) };
+
+        assert_eq!(test_item.to_query_string(), "a=1&b=2");
+        assert_eq!(test_item.params_len(), 2);
+        assert_eq!(TestItemRepeatedAttributes::QUERY_KEYS, &["a", "b"]);
+
+        for b in [-1, 101] {
+            let test_item = TestItemRepeatedAttributes { a: 1, b: Some(b) };
+            assert_eq!(test_item.to_query_string(), "a=1");
+        }
     }
 
-    #[derive(QueryParams, Debug, PartialEq)]
-    struct TestItemOptionals {
-        a: Option<String>,
-        b: Option<bool>,
+    #[test]
+    fn test_query_params_cow() {
+        let test_item = TestItemCow {
+            term: "red shoes".to_string(),
+            cursor: Some("abc".to_string()),
+            page: 2,
+            transformed: "t".to_string(),
+            order: None,
+            fallback: None,
+            address: Some(TestItemAddress {
+                city: "Paris".to_string(),
+                zip: None,
+            }),
+        };
+
+        let params = test_item.to_query_params_cow();
+
+        let owned: Vec<(String, String)> = params
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        assert_eq!(owned, test_item.to_query_params());
+
+        let borrowed: Vec<(bool, bool)> = params
+            .iter()
+            .map(|(key, value)| {
+                (
+                    matches!(key, Cow::Borrowed(_)),
+                    matches!(value, Cow::Borrowed(_)),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            borrowed,
+            vec![
+                (true, true),
+                (true, true),
+                (true, true),
+                (true, false),
+                (true, false),
+                (true, true),
+                (true, false),
+                (false, true),
+            ]
+        );
+        assert_eq!(params[7], (Cow::from("address[city]"), Cow::from("Paris")));
     }
 
-    #[derive(QueryParams, Debug, PartialEq)]
-    struct TestItemRename {
-        #[query(rename = "alpha")]
-        a: Option<String>,
-        #[query(rename = "beta")]
-        b: Option<bool>,
+    #[test]
+    fn test_query_params_cow_default() {
+        let test_item = TestItemHandWritten;
+
+        let params = test_item.to_query_params_cow();
+
+        assert_eq!(params, vec![(Cow::from("manual"), Cow::from("yes"))]);
+        assert!(matches!(params[0], (Cow::Owned(_), Cow::Owned(_))));
     }
 
-    #[derive(QueryParams, Debug, PartialEq)]
-    struct TestItemMixedRequiredOptionals {
-        a: Option<String>,
-        b: Option<bool>,
-        #[query(required)]
-        c: i32,
+    #[test]
+    fn test_indexed() {
+        let test_item = TestItemIndexed {
+            order: "o1".to_string(),
+            items: vec![
+                TestItemLineItem {
+                    product_id: 7,
+                    quantity: Some(2),
+                    address: None,
+                },
+                TestItemLineItem {
+                    product_id: 9,
+                    quantity: None,
+                    address: Some(TestItemAddress {
+                        city: "Paris".to_string(),
+                        zip: None,
+                    }),
+                },
+            ],
+            extras: Some(vec![TestItemAddress {
+                city: "Oslo".to_string(),
+                zip: Some("0150".to_string()),
+            }]),
+        };
+
+        let expected = vec![
+            ("order".to_string(), "o1".to_string()),
+            ("items[0][productId]".to_string(), "7".to_string()),
+            ("items[0][qty]".to_string(), "2".to_string()),
+            ("items[1][productId]".to_string(), "9".to_string()),
+            ("items[1][address][city]".to_string(), "Paris".to_string()),
+            ("extra[0][city]".to_string(), "Oslo".to_string()),
+            ("extra[0][zip]".to_string(), "0150".to_string()),
+        ];
+
+        assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.try_to_query_params().unwrap(), expected);
+        assert_eq!(test_item.params_len(), 7);
+        assert_eq!(TestItemIndexed::QUERY_KEYS, &["order"]);
+        assert_eq!(
+            test_item.to_encoded_params()[1].0,
+            "items%5B0%5D%5BproductId%5D"
+        );
+
+        let cow: Vec<(String, String)> = test_item
+            .to_query_params_cow()
+            .into_iter()
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect();
+        assert_eq!(cow, expected);
+
+        let test_item = TestItemIndexed {
+            order: "o2".to_string(),
+            items: Vec::new(),
+            extras: None,
+        };
+
+        assert_eq!(test_item.to_query_string(), "order=o2");
+        assert_eq!(test_item.params_len(), 1);
     }
 
-    #[derive(QueryParams, Debug, PartialEq)]
-    struct TestItemMixedRequiredOptionalsAndRename {
-        #[query(rename = "alpha")]
-        a: Option<String>,
-        b: Option<bool>,
-        #[query(rename = "gamma")]
-        #[query(required)]
-        c: i32,
+    #[test]
+    fn test_assert_query_eq() {
+        let test_item = TestItemMixedRequiredOptionals {
+            a: Some("a".to_string()),
+            b: None,
+            c: 3,
+        };
+
+        assert_query_eq!(test_item.to_query_params(), [("c", "3"), ("a", "a")]);
+        assert_query_eq!(test_item.to_query_params(), vec![("a", 'a'), ("c", '3')],);
+        assert_query_eq!(
+            test_item.to_query_params_cow(),
+            test_item.to_query_params(),
+            "cow params of {} differ",
+            "TestItemMixedRequiredOptionals",
+        );
+
+        let mut builder = QueryParamsBuilder::new();
+        builder.push("a", 1).push("b", 2).push("a", 1);
+        assert_query_eq!(builder.build(), [("a", "1"), ("a", "1"), ("b", "2")]);
     }
 
     #[test]
-    fn test_developer_experience() {
-        let t = trybuild::TestCases::new();
-        t.compile_fail("tests/ui/*.rs");
+    #[should_panic(expected = "query params differ, ignoring order")]
+    fn test_assert_query_eq_counts_duplicates() {
+        let mut builder = QueryParamsBuilder::new();
+        builder.push("a", 1).push("a", 1).push("b", 2);
+
+        assert_query_eq!(builder.build(), [("a", "1"), ("b", "2"), ("b", "2")]);
     }
 
     #[test]
-    fn test_query_params_required_case() {
-        let test_item = TestItem { a: 0, b: 1 };
+    fn test_flatten() {
+        let test_item = TestItemFlatten {
+            page: 2,
+            extra: BTreeMap::from([
+                ("sort by".to_string(), "a&b".to_string()),
+                ("page".to_string(), "3".to_string()),
+            ]),
+            secrets: Some(HashMap::from([("token", 42)])),
+        };
 
         let expected = vec![
-            ("a".to_string(), "0".to_string()),
-            ("b".to_string(), "1".to_string()),
+            ("page".to_string(), "2".to_string()),
+            ("page".to_string(), "3".to_string()),
+            ("sort by".to_string(), "a&b".to_string()),
+            ("token".to_string(), "42".to_string()),
         ];
 
         assert_eq!(test_item.to_query_params(), expected);
+        assert_eq!(test_item.try_to_query_params().unwrap(), expected);
+        assert_eq!(test_item.params_len(), 4);
+        assert_eq!(
+            test_item.to_query_string(),
+            "page=2&page=3&sort%20by=a%26b&token=42"
+        );
+        assert_eq!(
+            test_item.to_redacted_params()[3],
+            ("token".to_string(), "***".to_string())
+        );
+        assert_query_eq!(test_item.to_query_params_cow(), expected);
+        assert_eq!(TestItemFlatten::QUERY_KEYS, &["page"]);
+
+        let test_item = TestItemFlatten {
+            page: 1,
+            extra: BTreeMap::new(),
+            secrets: None,
+        };
+
+        assert_eq!(test_item.to_query_string(), "page=1");
+        assert_eq!(test_item.params_len(), 1);
     }
 
     #[test]
-    fn test_exclude_attribute() {
-        let test_item = TestExcludeItem {
-            a: 0,
-            b: 1,
-            c: Some(2),
-            d: 3,
+    fn test_encode_schemes() {
+        let value = "https://example.com/a b?c=d";
+        let test_item = TestItemEncodeSchemes {
+            standard: value.to_string(),
+            component: value.to_string(),
+            form: value.to_string(),
         };
 
         let expected = vec![
-            ("a".to_string(), "0".to_string()),
-            ("d".to_string(), "3".to_string()),
+            (
+                "standard".to_string(),
+                "https%3A%2F%2Fexample.com%2Fa%20b%3Fc%3Dd".to_string(),
+            ),
+            (
+                "component".to_string(),
+                "https://example.com/a%20b?c%3Dd".to_string(),
+            ),
+            (
+                "form+value".to_string(),
+                "https%3A%2F%2Fexample.com%2Fa+b%3Fc%3Dd".to_string(),
+            ),
+        ];
+
+        assert_eq!(test_item.to_encoded_params(), expected);
+        assert_eq!(
+            test_item.to_query_params(),
+            vec![
+                ("standard".to_string(), value.to_string()),
+                ("component".to_string(), value.to_string()),
+                ("form value".to_string(), value.to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_method_attribute() {
+        let test_item = TestItemMethod {
+            page: 2,
+            q: Some("red shoes".to_string()),
+        };
+
+        let expected = vec![
+            ("page".to_string(), "2".to_string()),
+            ("q".to_string(), "red shoes".to_string()),
+        ];
+
+        assert_eq!(test_item.as_query(), expected);
+        assert_eq!(ToQueryParams::to_query_params(&test_item), expected);
+        assert_eq!(LegacyQuery::to_query_params(&test_item), "legacy-2");
+
+        let test_item = TestItemRawMethod {
+            kind: "a".to_string(),
+        };
+
+        assert_eq!(test_item.r#type(), test_item.to_query_params());
+    }
+
+    #[cfg(feature = "utoipa")]
+    #[test]
+    fn test_utoipa_into_params() {
+        use utoipa::openapi::path::ParameterIn;
+        use utoipa::openapi::Required;
+        use utoipa::IntoParams;
+
+        let params = TestItemUtoipa::into_params(|| None);
+
+        // utoipa only implements Debug behind its `debug` feature, so the fields are compared as bools
+        let described: Vec<(&str, bool)> = params
+            .iter()
+            .map(|param| (param.name.as_str(), param.required == Required::True))
+            .collect();
+
+        assert_eq!(
+            described,
+            vec![
+                ("pageSize", true),
+                ("per_page", true),
+                ("q", true),
+                ("sort", true),
+                ("session", true),
+                ("filter", false),
+                ("mode", false),
+                ("offset", false),
+            ]
+        );
+        assert!(TestItemOwnIntoParams::into_params(|| None).is_empty());
+        assert!(params
+            .iter()
+            .all(|param| param.parameter_in == ParameterIn::Query && param.schema.is_some()));
+
+        let params = TestItemUtoipa::into_params(|| Some(ParameterIn::Header));
+        assert!(params[0].parameter_in == ParameterIn::Header);
+    }
+
+    #[test]
+    fn test_nested_keeps_child_encoding() {
+        let child = || TestItemRawChild {
+            token: "a%2Fb".to_string(),
+            form: Some("1 + 1".to_string()),
+            plain: "c d".to_string(),
+        };
+
+        let test_item = TestItemRawParent {
+            child: child(),
+            items: Some(vec![child()]),
+        };
+
+        let expected = vec![
+            ("the%20child%5Btoken%5D".to_string(), "a%2Fb".to_string()),
+            (
+                "the%20child%5Bform+value%5D".to_string(),
+                "1+%2B+1".to_string(),
+            ),
+            ("the%20child%5Bplain%5D".to_string(), "c%20d".to_string()),
+            ("items%5B0%5D%5Btoken%5D".to_string(), "a%2Fb".to_string()),
+            (
+                "items%5B0%5D%5Bform+value%5D".to_string(),
+                "1+%2B+1".to_string(),
+            ),
+            ("items%5B0%5D%5Bplain%5D".to_string(), "c%20d".to_string()),
+        ];
+
+        assert_eq!(test_item.to_encoded_params(), expected);
+        assert_eq!(
+            test_item.to_query_string(),
+            expected
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect::<Vec<String>>()
+                .join("&")
+        );
+        assert_eq!(
+            test_item.to_query_params()[0],
+            ("the child[token]".to_string(), "a%2Fb".to_string())
+        );
+    }
+
+    #[test]
+    fn test_derive_without_trait_import() {
+        let test_item = without_trait_import::TestItemNoTraitImport {
+            a: 1,
+            b: Some("x".to_string()),
+        };
+
+        let expected = vec![
+            ("a".to_string(), "1".to_string()),
+            ("b".to_string(), "x".to_string()),
         ];
 
         assert_eq!(test_item.to_query_params(), expected);
     }
 
     #[test]
-    fn test_query_params_encoding() {
+    fn test_crate_path_container_attribute() {
+        let test_item = TestItemCratePath {
+            a: "c d".to_string(),
+        };
+
+        assert_eq!(
+            test_item.to_encoded_params(),
+            vec![("a%20b".to_string(), "c%20d".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_write_query_string_into_reused_buffer() {
+        let mut buf = String::new();
+
         let test_item = TestStringItem {
             a: "please encode me".into(),
             b: "this works?".into(),
         };
 
+        test_item.write_query_string_into(&mut buf);
+        assert_eq!(
+            buf,
+            "a=please%20encode%20me&please%20encode=this%20works%3F"
+        );
+
+        let test_item = TestItem { a: 0, b: 1 };
+
+        test_item.write_query_string_into(&mut buf);
+        assert_eq!(buf, "a=0&b=1");
+    }
+
+    #[test]
+    fn test_form_encoded_params() {
+        let test_item = TestStringItem {
+            a: "please encode me".into(),
+            b: "1+1=2?".into(),
+        };
+
         let expected = vec![
-            ("a".to_string(), "please%20encode%20me".to_string()),
-            ("please%20encode".to_string(), "this%20works%3F".to_string()),
+            ("a".to_string(), "please+encode+me".to_string()),
+            ("please+encode".to_string(), "1%2B1%3D2%3F".to_string()),
         ];
 
-        assert_eq!(test_item.to_encoded_params(), expected);
+        assert_eq!(test_item.to_form_encoded_params(), expected);
+    }
+
+    #[test]
+    fn test_encoded_params_with() {
+        let test_item = TestStringItem {
+            a: "!*'()~ /?:".into(),
+            b: "a&b".into(),
+        };
+
+        let lenient = vec![
+            ("a".to_string(), "!*'()~%20/?:".to_string()),
+            ("please%20encode".to_string(), "a%26b".to_string()),
+        ];
+
+        let strict = vec![
+            ("a".to_string(), "%21%2A%27%28%29~%20%2F%3F%3A".to_string()),
+            ("please%20encode".to_string(), "a%26b".to_string()),
+        ];
+
+        let form = vec![
+            ("a".to_string(), "%21*%27%28%29%7E+%2F%3F%3A".to_string()),
+            ("please+encode".to_string(), "a%26b".to_string()),
+        ];
+
+        assert_eq!(
+            test_item.to_encoded_params_with(EncodeSet::QueryLenient),
+            lenient
+        );
+        assert_eq!(
+            test_item.to_encoded_params_with(EncodeSet::Rfc3986Strict),
+            strict
+        );
+        assert_eq!(
+            test_item.to_encoded_params_with(EncodeSet::FormUrlEncoded),
+            form
+        );
+        assert_eq!(test_item.to_encoded_params(), strict);
+    }
+
+    #[test]
+    fn test_oauth1_encoded_params_rfc_example() {
+        let test_item = TestItemOAuth1 {
+            b5: "=%3D".to_string(),
+            a3: "a".to_string(),
+            c_at: "".to_string(),
+            a2: "r b".to_string(),
+            oauth_consumer_key: "9djdj82h48djs9d2".to_string(),
+            oauth_token: "kkk9d7dh3k39sjv7".to_string(),
+            oauth_signature_method: "HMAC-SHA1".to_string(),
+            oauth_timestamp: "137131201".to_string(),
+            oauth_nonce: "7d8f3e4a".to_string(),
+            c2: "".to_string(),
+            a3_second: "2 q".to_string(),
+        };
+
+        let mut encoded = test_item.to_encoded_params_with(EncodeSet::OAuth1);
+        encoded.sort();
+
+        let normalized = encoded
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect::<Vec<String>>()
+            .join("&");
+
+        assert_eq!(
+            normalized,
+            "a2=r%20b&a3=2%20q&a3=a&b5=%3D%253D&c%40=&c2=&oauth_consumer_key=9dj\
+             dj82h48djs9d2&oauth_nonce=7d8f3e4a&oauth_signature_method=HMAC-SHA1\
+             &oauth_timestamp=137131201&oauth_token=kkk9d7dh3k39sjv7"
+        );
+    }
+
+    #[test]
+    fn test_form_body_matches_serde_urlencoded() {
+        let test_items = [
+            TestStringItem {
+                a: "please encode me".into(),
+                b: "1+1=2?".into(),
+            },
+            TestStringItem {
+                a: "*-._~!'()".into(),
+                b: "a&b=c/d café".into(),
+            },
+        ];
+
+        for test_item in test_items {
+            let expected = serde_urlencoded::to_string(test_item.to_query_params()).unwrap();
+
+            assert_eq!(test_item.to_form_body(), expected);
+        }
+    }
+
+    #[test]
+    fn test_canonical_query_string() {
+        let test_item = TestItemOAuth1 {
+            b5: "=%3D".to_string(),
+            a3: "a".to_string(),
+            c_at: "".to_string(),
+            a2: "r b".to_string(),
+            oauth_consumer_key: "9djdj82h48djs9d2".to_string(),
+            oauth_token: "kkk9d7dh3k39sjv7".to_string(),
+            oauth_signature_method: "HMAC-SHA1".to_string(),
+            oauth_timestamp: "137131201".to_string(),
+            oauth_nonce: "7d8f3e4a".to_string(),
+            c2: "it's (1+1)*2".to_string(),
+            a3_second: "2 q".to_string(),
+        };
+
+        assert_eq!(
+            test_item.to_canonical_query_string(),
+            "a2=r%20b&a3=2%20q&a3=a&b5=%3D%253D&c%40=&c2=it%27s%20%281%2B1%29%2A2&\
+             oauth_consumer_key=9djdj82h48djs9d2&oauth_nonce=7d8f3e4a&\
+             oauth_signature_method=HMAC-SHA1&oauth_timestamp=137131201&\
+             oauth_token=kkk9d7dh3k39sjv7"
+        );
+    }
+
+    #[test]
+    fn test_canonical_query_string_ignores_field_order() {
+        let test_item = TestItemOwned {
+            name: "Jo".to_string(),
+            count: 1,
+            note: None,
+            email: Some("x y".to_string()),
+            label: None,
+        };
+
+        assert_eq!(test_item.to_query_params()[0].0, "name");
+        assert_eq!(
+            test_item.to_canonical_query_string(),
+            "count=1&email=x%20y&name=Jo&tag=none"
+        );
+        assert_eq!(
+            TestItemOptionals { a: None, b: None }.to_canonical_query_string(),
+            ""
+        );
+    }
+
+    #[test]
+    fn test_form_body_empty() {
+        let test_item = TestItemOptionals { a: None, b: None };
+
+        assert_eq!(test_item.to_form_body(), "");
     }
 
     #[test]
@@ -194,12 +4955,36 @@ mod tests {
             ("c".to_string(), "42".to_string()),
         ];
 
-        let mut actual = test_item.to_query_params();
+        assert_eq!(test_item.to_query_params(), expected);
+    }
+
+    #[cfg(feature = "serde-compat")]
+    #[test]
+    fn test_serde_rename_fallback() {
+        let test_item = TestItemSerdeRename {
+            item_id: 1,
+            item_type: "accessory".to_string(),
+            max_price: Some(100),
+            min_price: Some(10),
+        };
+
+        let expected = vec![
+            ("ID".to_string(), "1".to_string()),
+            ("kind".to_string(), "accessory".to_string()),
+            ("maxPrice".to_string(), "100".to_string()),
+            ("minPrice".to_string(), "10".to_string()),
+        ];
 
-        // order is not guaranteed due to splitting up optional and required params
-        actual.sort();
+        assert_eq!(test_item.to_query_params(), expected);
+    }
 
-        assert_eq!(actual, expected);
+    #[cfg(feature = "serde-compat")]
+    #[test]
+    fn test_query_rename_all_over_serde_rename_all() {
+        assert_eq!(
+            TestItemSerdeAndQueryRenameAll::QUERY_KEYS,
+            &["ID", "max-price"]
+        );
     }
 
     #[test]
@@ -215,11 +5000,6 @@ mod tests {
             ("gamma".to_string(), "42".to_string()),
         ];
 
-        let mut actual = test_item.to_query_params();
-
-        // order is not guaranteed due to splitting up optional and required params
-        actual.sort();
-
-        assert_eq!(actual, expected);
+        assert_eq!(test_item.to_query_params(), expected);
     }
 }
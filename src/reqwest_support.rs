@@ -0,0 +1,79 @@
+//! Integration with [`reqwest`](https://crates.io/crates/reqwest) request builders.
+use crate::ToQueryParams;
+
+/// [`RequestBuilderExt`] adds `query_params` to reqwest's request builders, appending the
+/// un-encoded pairs from [`ToQueryParams::to_query_params`] so that reqwest encodes them exactly
+/// once.
+///
+/// It's implemented for `reqwest::RequestBuilder` with the `reqwest` feature enabled, and for
+/// `reqwest::blocking::RequestBuilder` with the `blocking` feature enabled too.
+pub trait RequestBuilderExt: Sized {
+    /// Appends the query parameters produced by `params` to the request's URL, keeping any query
+    /// parameters that were already added.
+    fn query_params<T: ToQueryParams + ?Sized>(self, params: &T) -> Self;
+}
+
+impl RequestBuilderExt for reqwest::RequestBuilder {
+    fn query_params<T: ToQueryParams + ?Sized>(self, params: &T) -> Self {
+        self.query(&params.to_query_params())
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl RequestBuilderExt for reqwest::blocking::RequestBuilder {
+    fn query_params<T: ToQueryParams + ?Sized>(self, params: &T) -> Self {
+        self.query(&params.to_query_params())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::QueryParams;
+
+    #[derive(QueryParams)]
+    struct TestItem {
+        #[query(required)]
+        a: String,
+        b: Option<i32>,
+    }
+
+    #[test]
+    fn test_query_params() {
+        let test_item = TestItem {
+            a: "a&b=c ü".to_string(),
+            b: None,
+        };
+
+        let request = reqwest::Client::new()
+            .get("https://api.example.com/items?sort=asc")
+            .query(&[("page", "2")])
+            .query_params(&test_item)
+            .query(&[("limit", "10")])
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            request.url().query(),
+            Some("sort=asc&page=2&a=a%26b%3Dc+%C3%BC&limit=10")
+        );
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_blocking_query_params() {
+        let test_item = TestItem {
+            a: "please encode me".to_string(),
+            b: Some(1),
+        };
+
+        let request = reqwest::blocking::Client::new()
+            .get("https://api.example.com/items")
+            .query(&[("page", "2")])
+            .query_params(&test_item)
+            .build()
+            .unwrap();
+
+        assert_eq!(request.url().query(), Some("page=2&a=please+encode+me&b=1"));
+    }
+}
@@ -0,0 +1,54 @@
+//! Integration with [`ureq`](https://crates.io/crates/ureq) requests.
+use crate::ToQueryParams;
+
+/// [`UreqRequestExt`] adds `query_params` to `ureq::Request`, adding each of the un-encoded pairs
+/// from [`ToQueryParams::to_query_params`] with `query`, so that ureq encodes them exactly once.
+///
+/// It's implemented with the `ureq` feature enabled.
+pub trait UreqRequestExt: Sized {
+    /// Appends the query parameters produced by `params` to the request's URL, keeping any query
+    /// parameters that were already added.
+    fn query_params<T: ToQueryParams + ?Sized>(self, params: &T) -> Self;
+}
+
+impl UreqRequestExt for ureq::Request {
+    fn query_params<T: ToQueryParams + ?Sized>(self, params: &T) -> Self {
+        params
+            .to_query_params()
+            .iter()
+            .fold(self, |request, (key, value)| request.query(key, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::QueryParams;
+
+    #[derive(QueryParams)]
+    struct TestItem {
+        #[query(required)]
+        a: String,
+        b: Option<i32>,
+    }
+
+    #[test]
+    fn test_ureq_query_params() {
+        let test_item = TestItem {
+            a: "a&b=c ü".to_string(),
+            b: Some(1),
+        };
+
+        let url = ureq::get("https://api.example.com/items?sort=asc")
+            .query("page", "2")
+            .query_params(&test_item)
+            .query("limit", "10")
+            .request_url()
+            .unwrap();
+
+        assert_eq!(
+            url.as_url().query(),
+            Some("sort=asc&page=2&a=a%26b%3Dc+%C3%BC&b=1&limit=10")
+        );
+    }
+}
@@ -0,0 +1,9 @@
+use to_query_params::QueryParams;
+
+#[derive(QueryParams)]
+struct Data {
+    #[query(pattern = "([a-z")]
+    slug: Option<String>,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use to_query_params::QueryParams;
+
+#[derive(QueryParams)]
+struct Request {
+    #[query(required, alias = "limit")]
+    page_size: u32,
+    limit: Option<u32>,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use to_query_params::QueryParams;
+
+#[derive(QueryParams)]
+struct Data {
+    #[query(bool_format = "yes/no")]
+    verbose: Option<u8>,
+}
+
+fn main() {}
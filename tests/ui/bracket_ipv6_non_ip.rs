@@ -0,0 +1,9 @@
+use to_query_params::QueryParams;
+
+#[derive(QueryParams)]
+struct Data {
+    #[query(required, bracket_ipv6)]
+    host: String,
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+use to_query_params::QueryParams;
+
+struct Color(u8);
+
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(QueryParams)]
+struct Data {
+    #[query(default)]
+    color: Option<Color>,
+}
+
+fn main() {}
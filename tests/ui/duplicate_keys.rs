@@ -0,0 +1,11 @@
+use to_query_params::QueryParams;
+
+#[derive(QueryParams)]
+struct Data {
+    #[query(rename = "price")]
+    min_price: Option<f64>,
+    #[query(rename = "price")]
+    max_price: Option<f64>,
+}
+
+fn main() {}
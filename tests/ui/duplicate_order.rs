@@ -0,0 +1,11 @@
+use to_query_params::QueryParams;
+
+#[derive(QueryParams)]
+struct Data {
+    #[query(required, order = 1)]
+    first: i32,
+    #[query(order = 1)]
+    second: Option<i32>,
+}
+
+fn main() {}
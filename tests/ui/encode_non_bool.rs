@@ -0,0 +1,9 @@
+use to_query_params::QueryParams;
+
+#[derive(QueryParams)]
+#[query(encode = "none")]
+struct Data {
+    number: Option<f64>,
+}
+
+fn main() {}
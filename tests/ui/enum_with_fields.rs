@@ -0,0 +1,9 @@
+use to_query_params::QueryParams;
+
+#[derive(QueryParams)]
+enum Filter {
+    All,
+    Price(i32),
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use to_query_params::QueryParams;
+
+#[derive(QueryParams)]
+#[query(exclusive("cursor", "pgae"))]
+struct Data {
+    cursor: Option<String>,
+    page: Option<u32>,
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use to_query_params::QueryParams;
+
+#[derive(QueryParams)]
+struct Item {
+    #[query(required)]
+    name: String,
+}
+
+#[derive(QueryParams)]
+struct Order {
+    #[query(required, indexed, nested)]
+    items: Vec<Item>,
+}
+
+fn main() {}
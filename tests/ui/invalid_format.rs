@@ -0,0 +1,9 @@
+use to_query_params::QueryParams;
+
+#[derive(QueryParams)]
+struct Data {
+    #[query(required, format = "{:.2} to {:.2}")]
+    number: f64,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use to_query_params::QueryParams;
+
+#[derive(QueryParams)]
+#[query(method = "type")]
+struct Data {
+    user_id: Option<i32>,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use to_query_params::QueryParams;
+
+#[derive(QueryParams)]
+struct Data {
+    #[query(required, min = "1")]
+    limit: u32,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use to_query_params::QueryParams;
+
+#[derive(QueryParams)]
+struct Data {
+    #[query(rename = 5)]
+    number: Option<f64>,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use to_query_params::QueryParams;
+
+#[derive(QueryParams)]
+struct Data {
+    #[query(rename)]
+    number: Option<f64>,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use to_query_params::QueryParams;
+
+#[derive(QueryParams)]
+struct Data {
+    #[query(required)]
+    limit: Option<u32>,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use to_query_params::QueryParams;
+
+#[derive(QueryParams)]
+#[query(sorted)]
+struct Data {
+    #[query(required, order = 1)]
+    first: i32,
+    second: Option<i32>,
+}
+
+fn main() {}
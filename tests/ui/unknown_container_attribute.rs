@@ -0,0 +1,9 @@
+use to_query_params::QueryParams;
+
+#[derive(QueryParams)]
+#[query(sort_keys)]
+struct Data {
+    number: Option<f64>,
+}
+
+fn main() {}
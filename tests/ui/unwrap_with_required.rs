@@ -0,0 +1,9 @@
+use to_query_params::QueryParams;
+
+#[derive(QueryParams)]
+struct Data {
+    #[query(required, unwrap)]
+    first: Option<i32>,
+}
+
+fn main() {}